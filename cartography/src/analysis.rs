@@ -0,0 +1,674 @@
+// graph statistics of each galaxy and of the whole universe
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt,
+};
+
+use serde::Serialize;
+
+use crate::universe::{Galaxy, Planet};
+
+// How many breadth first searches the diameter estimate runs at most
+const DIAMETER_SWEEPS: usize = 4;
+// How many planets shortest paths are followed from to estimate betweenness
+const BETWEENNESS_SAMPLES: usize = 100;
+const PAGERANK_DAMPING: f64 = 0.85;
+const PAGERANK_MAX_ITERATIONS: usize = 100;
+// PageRank stops once the ranks move less than this in total
+const PAGERANK_TOLERANCE: f64 = 1e-9;
+
+// How central a planet is in the universe
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Centrality {
+    // Weighted PageRank, how likely a walk along the connections is to be on the planet
+    pub pagerank: f64,
+    // Share of the shortest paths between other planets that go through it, estimated from a sample
+    pub betweenness: f64,
+    // Sum of the weights of its connections
+    pub weighted_degree: f64,
+}
+
+// Summary of a set of values, e.g. the degree of every planet
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Distribution {
+    pub min: u64,
+    pub median: u64,
+    pub mean: f64,
+    pub max: u64,
+    // How many values fall in 1, 2-3, 4-7, 8-15...
+    pub histogram: Vec<usize>,
+}
+
+impl Distribution {
+    fn new(mut values: Vec<u64>) -> Distribution {
+        if values.is_empty() {
+            return Distribution::default();
+        }
+        values.sort_unstable();
+        let mut histogram = vec![];
+        for value in values.iter() {
+            // Zero shares the first bucket with one
+            let bucket = (63 - value.max(&1).leading_zeros()) as usize;
+            if histogram.len() <= bucket {
+                histogram.resize(bucket + 1, 0);
+            }
+            histogram[bucket] += 1;
+        }
+        Distribution {
+            min: values[0],
+            median: values[values.len() / 2],
+            mean: values.iter().sum::<u64>() as f64 / values.len() as f64,
+            max: values[values.len() - 1],
+            histogram,
+        }
+    }
+
+    // e.g. "1:120 2:80 4:40", each bucket named after the smallest value in it
+    pub fn histogram_text(&self) -> String {
+        self.histogram
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(bucket, count)| format!("{}:{}", 1u64 << bucket, count))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl fmt::Display for Distribution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "min {}, median {}, mean {:.1}, max {}",
+            self.min, self.median, self.mean, self.max
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GraphStats {
+    pub planets: usize,
+    pub connections: usize,
+    pub degree: Distribution,
+    pub weight: Distribution,
+    pub components: usize,
+    // Planets in the largest connected component
+    pub largest_component: usize,
+    // Longest shortest path found in the largest component, in hops.
+    //  Only a few searches are run, so the real diameter may be longer
+    pub diameter: usize,
+    // Average local clustering coefficient, how often a planet's neighbours are connected
+    pub clustering: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GalaxyStats {
+    pub name: String,
+    #[serde(flatten)]
+    pub graph: GraphStats,
+    // How many tags this galaxy shares with each other galaxy
+    pub shared_tags: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UniverseStats {
+    pub galaxies: Vec<GalaxyStats>,
+    #[serde(flatten)]
+    pub graph: GraphStats,
+    // Planets that belong to more than one galaxy
+    pub shared_planets: usize,
+}
+
+// Undirected graph of planets, indexed by number for the statistics
+struct Graph {
+    adjacency: Vec<Vec<usize>>,
+    // Weight of each connection in adjacency
+    adjacent_weights: Vec<Vec<u64>>,
+    weights: Vec<u64>,
+}
+
+impl Graph {
+    // Connections may be listed from both ends or by several galaxies,
+    //  each pair of planets is kept once with its heaviest weight
+    fn new<'a>(
+        planets: impl IntoIterator<Item = &'a str>,
+        connections: impl IntoIterator<Item = (&'a str, &'a str, i32)>,
+    ) -> Graph {
+        let mut index: HashMap<&str, usize> = HashMap::new();
+        let mut index_of = |name: &'a str| {
+            let next = index.len();
+            *index.entry(name).or_insert(next)
+        };
+        for planet in planets {
+            index_of(planet);
+        }
+        let mut pairs: HashMap<(usize, usize), i32> = HashMap::new();
+        for (a, b, weight) in connections {
+            let (a, b) = (index_of(a), index_of(b));
+            if a == b {
+                continue;
+            }
+            let heaviest = pairs.entry((a.min(b), a.max(b))).or_insert(weight);
+            *heaviest = (*heaviest).max(weight);
+        }
+
+        let mut adjacency = vec![vec![]; index.len()];
+        let mut adjacent_weights = vec![vec![]; index.len()];
+        let mut weights = vec![];
+        for (&(a, b), &weight) in pairs.iter() {
+            let weight = weight.max(0) as u64;
+            adjacency[a].push(b);
+            adjacency[b].push(a);
+            adjacent_weights[a].push(weight);
+            adjacent_weights[b].push(weight);
+            weights.push(weight);
+        }
+        Graph {
+            adjacency,
+            adjacent_weights,
+            weights,
+        }
+    }
+
+    fn stats(&self) -> GraphStats {
+        let components = self.components();
+        let largest = components.iter().max_by_key(|(_, size)| *size);
+        GraphStats {
+            planets: self.adjacency.len(),
+            connections: self.weights.len(),
+            degree: Distribution::new(self.adjacency.iter().map(|n| n.len() as u64).collect()),
+            weight: Distribution::new(self.weights.clone()),
+            components: components.len(),
+            largest_component: largest.map_or(0, |(_, size)| *size),
+            diameter: largest.map_or(0, |(start, _)| self.estimate_diameter(*start)),
+            clustering: self.clustering(),
+        }
+    }
+
+    // A planet of each connected component and how many planets are in it
+    fn components(&self) -> Vec<(usize, usize)> {
+        let mut seen = vec![false; self.adjacency.len()];
+        let mut components = vec![];
+        for start in 0..self.adjacency.len() {
+            if seen[start] {
+                continue;
+            }
+            seen[start] = true;
+            let mut stack = vec![start];
+            let mut size = 0;
+            while let Some(planet) = stack.pop() {
+                size += 1;
+                for &next in self.adjacency[planet].iter() {
+                    if !seen[next] {
+                        seen[next] = true;
+                        stack.push(next);
+                    }
+                }
+            }
+            components.push((start, size));
+        }
+        components
+    }
+
+    // The planet furthest from start in hops, and how far it is
+    fn furthest_from(&self, start: usize) -> (usize, usize) {
+        let mut hops = vec![usize::MAX; self.adjacency.len()];
+        hops[start] = 0;
+        let mut queue = VecDeque::from(vec![start]);
+        let mut furthest = (start, 0);
+        while let Some(planet) = queue.pop_front() {
+            if hops[planet] > furthest.1 {
+                furthest = (planet, hops[planet]);
+            }
+            for &next in self.adjacency[planet].iter() {
+                if hops[next] == usize::MAX {
+                    hops[next] = hops[planet] + 1;
+                    queue.push_back(next);
+                }
+            }
+        }
+        furthest
+    }
+
+    // Repeated sweeps from the furthest planet found so far,
+    //  the ends of the longest shortest path are usually found in a couple
+    fn estimate_diameter(&self, start: usize) -> usize {
+        let (mut from, mut diameter) = self.furthest_from(start);
+        for _ in 1..DIAMETER_SWEEPS {
+            let (furthest, hops) = self.furthest_from(from);
+            if hops <= diameter {
+                break;
+            }
+            diameter = hops;
+            from = furthest;
+        }
+        diameter
+    }
+
+    fn weighted_degrees(&self) -> Vec<f64> {
+        self.adjacent_weights
+            .iter()
+            .map(|weights| weights.iter().sum::<u64>() as f64)
+            .collect()
+    }
+
+    // A walk follows heavier connections more often, and jumps anywhere from planets without any
+    fn pagerank(&self, weighted_degrees: &[f64]) -> Vec<f64> {
+        let count = self.adjacency.len();
+        let mut rank = vec![1. / count as f64; count];
+        for _ in 0..PAGERANK_MAX_ITERATIONS {
+            let stranded: f64 = rank
+                .iter()
+                .zip(weighted_degrees.iter())
+                .filter(|(_, degree)| **degree == 0.)
+                .map(|(rank, _)| rank)
+                .fold(0., |total, rank| total + rank);
+            let base = (1. - PAGERANK_DAMPING + PAGERANK_DAMPING * stranded) / count as f64;
+            let mut next = vec![base; count];
+            for (planet, neighbours) in self.adjacency.iter().enumerate() {
+                if weighted_degrees[planet] == 0. {
+                    continue;
+                }
+                let share = PAGERANK_DAMPING * rank[planet] / weighted_degrees[planet];
+                for (&next_planet, &weight) in
+                    neighbours.iter().zip(self.adjacent_weights[planet].iter())
+                {
+                    next[next_planet] += share * weight as f64;
+                }
+            }
+            let moved: f64 = next
+                .iter()
+                .zip(rank.iter())
+                .map(|(a, b)| (a - b).abs())
+                .fold(0., |total, moved| total + moved);
+            rank = next;
+            if moved < PAGERANK_TOLERANCE {
+                break;
+            }
+        }
+        rank
+    }
+
+    // Brandes' algorithm over hops, run from evenly spread planets only
+    //  and scaled up as if it had been run from all of them
+    fn betweenness(&self) -> Vec<f64> {
+        let count = self.adjacency.len();
+        let mut betweenness = vec![0.; count];
+        if count < 3 {
+            return betweenness;
+        }
+        let sources: Vec<usize> = (0..count)
+            .step_by((count / BETWEENNESS_SAMPLES).max(1))
+            .collect();
+        for &source in sources.iter() {
+            let mut hops = vec![usize::MAX; count];
+            // How many shortest paths reach each planet, and from which planets
+            let mut paths = vec![0.; count];
+            let mut previous: Vec<Vec<usize>> = vec![vec![]; count];
+            let mut visited = vec![];
+            hops[source] = 0;
+            paths[source] = 1.;
+            let mut queue = VecDeque::from(vec![source]);
+            while let Some(planet) = queue.pop_front() {
+                visited.push(planet);
+                for &next in self.adjacency[planet].iter() {
+                    if hops[next] == usize::MAX {
+                        hops[next] = hops[planet] + 1;
+                        queue.push_back(next);
+                    }
+                    if hops[next] == hops[planet] + 1 {
+                        paths[next] += paths[planet];
+                        previous[next].push(planet);
+                    }
+                }
+            }
+
+            let mut dependency = vec![0.; count];
+            while let Some(planet) = visited.pop() {
+                for &before in previous[planet].iter() {
+                    dependency[before] += paths[before] / paths[planet] * (1. + dependency[planet]);
+                }
+                if planet != source {
+                    betweenness[planet] += dependency[planet];
+                }
+            }
+        }
+
+        // Each path is found from both of its ends, and divided by the pairs of other planets
+        let pairs = ((count - 1) * (count - 2)) as f64 / 2.;
+        let scale = count as f64 / sources.len() as f64 / 2. / pairs;
+        betweenness.iter().map(|b| b * scale).collect()
+    }
+
+    fn clustering(&self) -> f64 {
+        let count = self.adjacency.len();
+        if count == 0 {
+            return 0.;
+        }
+        // Connections are pointed towards the busier planet, so each triangle is found once
+        //  and busy planets don't have all their neighbours compared with each other
+        let rank = |planet: usize| (self.adjacency[planet].len(), planet);
+        let ahead: Vec<Vec<usize>> = (0..count)
+            .map(|planet| {
+                self.adjacency[planet]
+                    .iter()
+                    .copied()
+                    .filter(|&next| rank(next) > rank(planet))
+                    .collect()
+            })
+            .collect();
+
+        let mut triangles = vec![0usize; count];
+        let mut marked = vec![false; count];
+        for (a, a_ahead) in ahead.iter().enumerate() {
+            for &b in a_ahead.iter() {
+                marked[b] = true;
+            }
+            for &b in a_ahead.iter() {
+                for &c in ahead[b].iter().filter(|&&c| marked[c]) {
+                    triangles[a] += 1;
+                    triangles[b] += 1;
+                    triangles[c] += 1;
+                }
+            }
+            for &b in a_ahead.iter() {
+                marked[b] = false;
+            }
+        }
+
+        let total: f64 = self
+            .adjacency
+            .iter()
+            .zip(triangles.iter())
+            .filter(|(neighbours, _)| neighbours.len() > 1)
+            .map(|(neighbours, &triangles)| {
+                let degree = neighbours.len();
+                2. * triangles as f64 / (degree * (degree - 1)) as f64
+            })
+            .fold(0., |total, local| total + local);
+        total / count as f64
+    }
+}
+
+// Every connection a galaxy's tags make, listed from both ends
+fn galaxy_connections(galaxy: &Galaxy) -> impl Iterator<Item = (&str, &str, i32)> {
+    galaxy
+        .relation
+        .relation_map
+        .iter()
+        .flat_map(|(tag, connected)| {
+            connected
+                .iter()
+                .map(move |t| (tag.as_str(), t.name.as_str(), t.count))
+        })
+}
+
+// Every pair of connected planets, a before b, with the heaviest weight a galaxy gives it.
+//  The same connections as the universe graph the statistics and centrality are measured on
+pub fn universe_connections(galaxies: &HashMap<String, Galaxy>) -> BTreeMap<(&str, &str), i32> {
+    let mut pairs: BTreeMap<(&str, &str), i32> = BTreeMap::new();
+    for (a, b, weight) in galaxies.values().flat_map(galaxy_connections) {
+        if a == b {
+            continue;
+        }
+        let heaviest = pairs.entry((a.min(b), a.max(b))).or_insert(weight);
+        *heaviest = (*heaviest).max(weight);
+    }
+    pairs
+}
+
+// The graphs of every galaxy and of the whole universe. They don't borrow the universe,
+//  so they can be measured on another thread
+pub struct UniverseGraphs {
+    galaxies: Vec<(String, Graph)>,
+    universe: Graph,
+    shared_tags: BTreeMap<String, BTreeMap<String, usize>>,
+    shared_planets: usize,
+}
+
+impl UniverseGraphs {
+    pub fn new(
+        galaxies: &HashMap<String, Galaxy>,
+        planets: &HashMap<String, Planet>,
+    ) -> UniverseGraphs {
+        let mut galaxy_graphs: Vec<(String, Graph)> = galaxies
+            .values()
+            .map(|galaxy| {
+                let graph = Graph::new(
+                    galaxy.relation.relation_map.keys().map(|tag| tag.as_str()),
+                    galaxy_connections(galaxy),
+                );
+                (galaxy.name.clone(), graph)
+            })
+            .collect();
+        galaxy_graphs.sort_by(|a, b| a.0.cmp(&b.0));
+
+        // Read from the tags of every galaxy rather than the planets' connections,
+        //  so it has the same connections as the galaxy graphs put together
+        let universe = Graph::new(
+            planets.keys().map(|name| name.as_str()),
+            galaxies.values().flat_map(galaxy_connections),
+        );
+
+        let mut shared_tags: BTreeMap<String, BTreeMap<String, usize>> = BTreeMap::new();
+        for planet in planets.values().filter(|p| p.belong_galaxy.len() > 1) {
+            for galaxy in planet.belong_galaxy.iter() {
+                let shared = shared_tags.entry(galaxy.clone()).or_default();
+                for other in planet.belong_galaxy.iter().filter(|g| *g != galaxy) {
+                    *shared.entry(other.clone()).or_default() += 1;
+                }
+            }
+        }
+
+        UniverseGraphs {
+            galaxies: galaxy_graphs,
+            universe,
+            shared_planets: planets
+                .values()
+                .filter(|p| p.belong_galaxy.len() > 1)
+                .count(),
+            shared_tags,
+        }
+    }
+
+    pub fn stats(&self) -> UniverseStats {
+        UniverseStats {
+            galaxies: self
+                .galaxies
+                .iter()
+                .map(|(name, graph)| GalaxyStats {
+                    name: name.clone(),
+                    graph: graph.stats(),
+                    shared_tags: self.shared_tags.get(name).cloned().unwrap_or_default(),
+                })
+                .collect(),
+            graph: self.universe.stats(),
+            shared_planets: self.shared_planets,
+        }
+    }
+}
+
+pub fn universe_stats(
+    galaxies: &HashMap<String, Galaxy>,
+    planets: &HashMap<String, Planet>,
+) -> UniverseStats {
+    UniverseGraphs::new(galaxies, planets).stats()
+}
+
+// Work out how central every planet is in the whole universe.
+//  Not done by build_universe, as betweenness takes a while on large universes
+pub fn measure_centrality(
+    galaxies: &HashMap<String, Galaxy>,
+    planets: &mut HashMap<String, Planet>,
+) {
+    // Sorted so the planets betweenness is sampled from are the same each time
+    let mut names: Vec<&String> = planets.keys().collect();
+    names.sort();
+    // Same connections as the universe graph of the statistics
+    let graph = Graph::new(
+        names.iter().map(|name| name.as_str()),
+        galaxies.values().flat_map(galaxy_connections),
+    );
+    if names.is_empty() {
+        return;
+    }
+
+    let weighted_degrees = graph.weighted_degrees();
+    let pagerank = graph.pagerank(&weighted_degrees);
+    let betweenness = graph.betweenness();
+    // Planets were indexed first, in the order of their names
+    let measured: Vec<(String, Centrality)> = names
+        .into_iter()
+        .enumerate()
+        .map(|(index, name)| {
+            let centrality = Centrality {
+                pagerank: pagerank[index],
+                betweenness: betweenness[index],
+                weighted_degree: weighted_degrees[index],
+            };
+            (name.clone(), centrality)
+        })
+        .collect();
+    for (name, centrality) in measured {
+        if let Some(planet) = planets.get_mut(&name) {
+            planet.centrality = Some(centrality);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::universe::{build_universe, tests::relation};
+
+    fn graph(edges: &[(&'static str, &'static str, i32)]) -> Graph {
+        Graph::new(std::iter::empty(), edges.iter().copied())
+    }
+
+    #[test]
+    fn diameter_of_a_path() {
+        let path = graph(&[("a", "b", 1), ("b", "c", 1), ("c", "d", 1), ("d", "e", 1)]);
+        // From the middle too, the sweeps find the ends
+        for start in 0..5 {
+            assert_eq!(path.estimate_diameter(start), 4);
+        }
+        assert_eq!(path.stats().diameter, 4);
+    }
+
+    #[test]
+    fn clustering_of_a_triangle_with_a_pendant() {
+        // a and b close their only triangle, c closes one of its three pairs, d has no pairs
+        let g = graph(&[("a", "b", 1), ("b", "c", 1), ("c", "a", 1), ("c", "d", 1)]);
+        assert!((g.clustering() - 7. / 12.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn components_of_disjoint_edges() {
+        let stats = graph(&[("a", "b", 1), ("c", "d", 1)]).stats();
+        assert_eq!(stats.components, 2);
+        assert_eq!(stats.largest_component, 2);
+        assert_eq!(stats.connections, 2);
+    }
+
+    #[test]
+    fn connections_are_kept_once_with_their_heaviest_weight() {
+        let g = graph(&[("a", "b", 3), ("b", "a", 5), ("a", "a", 9)]);
+        assert_eq!(g.weights, vec![5]);
+    }
+
+    #[test]
+    fn histogram_buckets() {
+        let distribution = Distribution::new(vec![0, 1, 2, 3, 4, 7, 8]);
+        // 0-1, 2-3, 4-7, 8-15
+        assert_eq!(distribution.histogram, vec![2, 2, 2, 1]);
+        assert_eq!(distribution.histogram_text(), "1:2 2:2 4:2 8:1");
+        assert_eq!(distribution.min, 0);
+        assert_eq!(distribution.median, 3);
+        assert_eq!(distribution.max, 8);
+
+        // Empty buckets are left out of the text
+        let sparse = Distribution::new(vec![1, 9]);
+        assert_eq!(sparse.histogram, vec![1, 0, 0, 1]);
+        assert_eq!(sparse.histogram_text(), "1:1 8:1");
+    }
+
+    fn star() -> Graph {
+        graph(&[
+            ("hub", "a", 1),
+            ("hub", "b", 1),
+            ("hub", "c", 1),
+            ("hub", "d", 1),
+        ])
+    }
+
+    #[test]
+    fn pagerank_sums_to_one_with_a_dangling_planet() {
+        let g = Graph::new(
+            ["lonely"],
+            [("a", "b", 2), ("b", "c", 1), ("c", "a", 5)]
+                .iter()
+                .copied(),
+        );
+        let degrees = g.weighted_degrees();
+        assert_eq!(degrees[0], 0.);
+        let rank = g.pagerank(&degrees);
+        let total = rank.iter().fold(0., |total, rank| total + rank);
+        assert!((total - 1.).abs() < 1e-9);
+        assert!(rank.iter().all(|rank| *rank > 0.));
+    }
+
+    #[test]
+    fn pagerank_of_a_star_peaks_at_the_hub() {
+        let g = star();
+        let rank = g.pagerank(&g.weighted_degrees());
+        // The hub is indexed first
+        assert!(rank[1..].iter().all(|leaf| rank[0] > *leaf));
+        assert!(rank[1..].iter().all(|leaf| (leaf - rank[1]).abs() < 1e-12));
+    }
+
+    #[test]
+    fn betweenness_matches_brandes_when_every_planet_is_sampled() {
+        // The hub is on the only path between each of the 6 pairs of leaves
+        let star = star().betweenness();
+        assert!((star[0] - 1.).abs() < 1e-12);
+        assert!(star[1..].iter().all(|leaf| leaf.abs() < 1e-12));
+
+        // a-b-c-d-e: c is on 4 of the 6 paths between the others, b on 3
+        let path = graph(&[("a", "b", 1), ("b", "c", 1), ("c", "d", 1), ("d", "e", 1)]);
+        let expected = [0., 0.5, 2. / 3., 0.5, 0.];
+        for (found, expected) in path.betweenness().iter().zip(expected.iter()) {
+            assert!((found - expected).abs() < 1e-12);
+        }
+
+        // a-b-c-d-a: a and c have two shortest paths, b gets half of one of 3 pairs
+        let square = graph(&[("a", "b", 1), ("b", "c", 1), ("c", "d", 1), ("d", "a", 1)]);
+        for found in square.betweenness() {
+            assert!((found - 1. / 6.).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn universe_graph_joins_galaxies_sharing_a_tag() {
+        let (galaxies, planets) = build_universe(vec![
+            relation("a", &[("x", "y", 10), ("y", "v", 3)]),
+            relation("b", &[("x", "y", 20), ("x", "z", 5), ("z", "w", 1)]),
+        ]);
+        let stats = universe_stats(&galaxies, &planets);
+        let graph = &stats.graph;
+        // x-y is made by both galaxies and kept once, with b's weight
+        assert_eq!(graph.planets, 5);
+        assert_eq!(graph.connections, 4);
+        assert_eq!(graph.weight.max, 20);
+        assert_eq!(graph.components, 1);
+        // v-y-x-z-w
+        assert_eq!(graph.diameter, 4);
+        assert_eq!(stats.shared_planets, 2);
+
+        let a = &stats.galaxies[0];
+        assert_eq!((a.name.as_str(), a.graph.connections), ("a", 2));
+        assert_eq!(a.graph.weight.max, 10);
+        assert_eq!(a.shared_tags["b"], 2);
+        let b = &stats.galaxies[1];
+        assert_eq!((b.name.as_str(), b.graph.connections), ("b", 3));
+    }
+}
@@ -0,0 +1,325 @@
+// constellations: groups of planets more connected to each other than to the rest of the universe,
+//  found with the Louvain method
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    analysis::universe_connections,
+    universe::{Galaxy, Planet},
+};
+
+// Most passes over every node at each level of the Louvain method
+const MAX_PASSES: usize = 20;
+// Smallest modularity gain worth moving a node for, so rounding doesn't shuffle nodes forever
+const MIN_GAIN: f64 = 1e-12;
+
+// Weighted graph of planets, or of the communities found at the level below
+struct Level {
+    // Neighbours and connection weights, sorted by neighbour so the result doesn't depend on hashing
+    adjacency: Vec<Vec<(usize, f64)>>,
+    // Weight of the connections inside each node, once communities are merged into nodes
+    inner: Vec<f64>,
+}
+
+impl Level {
+    // Connections inside a node count from both of their ends
+    fn degrees(&self) -> Vec<f64> {
+        self.adjacency
+            .iter()
+            .zip(self.inner.iter())
+            .map(|(neighbours, inner)| neighbours.iter().map(|(_, w)| w).sum::<f64>() + 2. * inner)
+            .collect()
+    }
+
+    // Move each node to the neighbouring community that raises modularity the most,
+    //  until none moves. None if no node moved at all
+    fn local_moves(&self) -> Option<Vec<usize>> {
+        let degrees = self.degrees();
+        let total_degree: f64 = degrees.iter().sum();
+        if total_degree == 0. {
+            return None;
+        }
+        let mut community: Vec<usize> = (0..self.adjacency.len()).collect();
+        // Summed degree of the nodes in each community
+        let mut community_degrees = degrees.clone();
+
+        let mut moved_any = false;
+        for _ in 0..MAX_PASSES {
+            let mut moved = false;
+            for (node, neighbours) in self.adjacency.iter().enumerate() {
+                let current = community[node];
+                let mut links: BTreeMap<usize, f64> = BTreeMap::new();
+                for &(next, weight) in neighbours.iter() {
+                    *links.entry(community[next]).or_default() += weight;
+                }
+                community_degrees[current] -= degrees[node];
+
+                let gain = |to: usize, weight: f64| {
+                    weight - community_degrees[to] * degrees[node] / total_degree
+                };
+                let mut best = (
+                    current,
+                    gain(current, links.get(&current).copied().unwrap_or(0.)),
+                );
+                for (&to, &weight) in links.iter() {
+                    let to_gain = gain(to, weight);
+                    if to_gain > best.1 + MIN_GAIN {
+                        best = (to, to_gain);
+                    }
+                }
+
+                community_degrees[best.0] += degrees[node];
+                if best.0 != current {
+                    community[node] = best.0;
+                    moved = true;
+                }
+            }
+            if !moved {
+                break;
+            }
+            moved_any = true;
+        }
+        if moved_any {
+            Some(community)
+        } else {
+            None
+        }
+    }
+
+    // One node per community, keeping the weight of the connections between and inside them
+    fn merge(&self, community: &[usize], count: usize) -> Level {
+        let mut inner = vec![0.; count];
+        let mut between: BTreeMap<(usize, usize), f64> = BTreeMap::new();
+        for (node, neighbours) in self.adjacency.iter().enumerate() {
+            let from = community[node];
+            inner[from] += self.inner[node];
+            for &(next, weight) in neighbours.iter() {
+                let to = community[next];
+                if from == to {
+                    // Seen once from each end
+                    inner[from] += weight / 2.;
+                } else {
+                    *between.entry((from, to)).or_default() += weight;
+                }
+            }
+        }
+        let mut adjacency = vec![vec![]; count];
+        for ((from, to), weight) in between {
+            adjacency[from].push((to, weight));
+        }
+        Level { adjacency, inner }
+    }
+}
+
+// Number communities 0, 1, 2... in the order they first appear
+fn renumber(community: &[usize]) -> (Vec<usize>, usize) {
+    let mut numbers: HashMap<usize, usize> = HashMap::new();
+    let renumbered = community
+        .iter()
+        .map(|c| {
+            let next = numbers.len();
+            *numbers.entry(*c).or_insert(next)
+        })
+        .collect();
+    (renumbered, numbers.len())
+}
+
+// Put every planet in a constellation, named after its planet with the highest PageRank,
+//  so centrality should be measured first
+pub fn find_constellations(
+    galaxies: &HashMap<String, Galaxy>,
+    planets: &mut HashMap<String, Planet>,
+) {
+    let mut names: Vec<String> = planets.keys().cloned().collect();
+    names.sort();
+    let index: HashMap<&str, usize> = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.as_str(), i))
+        .collect();
+
+    // Pairs connected by several galaxies already keep their heaviest connection
+    let mut pairs: BTreeMap<(usize, usize), f64> = BTreeMap::new();
+    for ((a, b), weight) in universe_connections(galaxies) {
+        let (a, b) = match (index.get(a), index.get(b)) {
+            (Some(&a), Some(&b)) => (a.min(b), a.max(b)),
+            _ => continue,
+        };
+        pairs.insert((a, b), weight.max(0) as f64);
+    }
+    let mut adjacency = vec![vec![]; names.len()];
+    for (&(a, b), &weight) in pairs.iter() {
+        adjacency[a].push((b, weight));
+        adjacency[b].push((a, weight));
+    }
+
+    let mut membership: Vec<usize> = (0..names.len()).collect();
+    let mut level = Level {
+        inner: vec![0.; names.len()],
+        adjacency,
+    };
+    while let Some(community) = level.local_moves() {
+        let (community, count) = renumber(&community);
+        if count == level.adjacency.len() {
+            break;
+        }
+        membership = membership.iter().map(|&node| community[node]).collect();
+        level = level.merge(&community, count);
+    }
+
+    // Names are sorted, so ties go to the first name
+    let mut brightest: HashMap<usize, &String> = HashMap::new();
+    for (name, &constellation) in names.iter().zip(membership.iter()) {
+        let pagerank = |name: &String| planets[name].centrality.map_or(0., |c| c.pagerank);
+        let brighter = match brightest.get(&constellation) {
+            Some(current) => pagerank(name) > pagerank(current),
+            None => true,
+        };
+        if brighter {
+            brightest.insert(constellation, name);
+        }
+    }
+    let named: Vec<(String, String)> = names
+        .iter()
+        .zip(membership.iter())
+        .map(|(name, constellation)| (name.clone(), brightest[constellation].clone()))
+        .collect();
+    for (name, constellation) in named {
+        if let Some(planet) = planets.get_mut(&name) {
+            planet.constellation = Some(constellation);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{
+        analysis::measure_centrality,
+        data::MetaRelation,
+        universe::{build_universe, tests::relation},
+    };
+
+    fn universe(edges: &[(&str, &str, i32)], lonely: &[&str]) -> HashMap<String, Planet> {
+        let mut relation = relation("test", edges);
+        for name in lonely {
+            relation.relation_map.insert(name.to_string(), vec![]);
+        }
+        measured(vec![relation])
+    }
+
+    fn measured(relations: Vec<MetaRelation>) -> HashMap<String, Planet> {
+        let (galaxies, mut planets) = build_universe(relations);
+        measure_centrality(&galaxies, &mut planets);
+        find_constellations(&galaxies, &mut planets);
+        planets
+    }
+
+    // Two cliques of four held together by a single weak connection
+    fn cliques() -> Vec<(&'static str, &'static str, i32)> {
+        let mut edges = vec![("a1", "b1", 1)];
+        for clique in [["a1", "a2", "a3", "a4"], ["b1", "b2", "b3", "b4"]] {
+            for (i, a) in clique.iter().enumerate() {
+                for b in clique[i + 1..].iter() {
+                    edges.push((a, b, 10));
+                }
+            }
+        }
+        edges
+    }
+
+    fn constellations(planets: &HashMap<String, Planet>) -> BTreeMap<String, String> {
+        planets
+            .values()
+            .map(|p| (p.name.clone(), p.constellation.clone().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn weakly_joined_cliques_are_two_constellations() {
+        let planets = universe(&cliques(), &[]);
+        let found = constellations(&planets);
+        for clique in ["a", "b"] {
+            let members: Vec<&String> = found.keys().filter(|n| n.starts_with(clique)).collect();
+            let brightest = members
+                .iter()
+                .max_by(|x, y| {
+                    let rank = |name: &String| planets[name].centrality.unwrap().pagerank;
+                    rank(x).partial_cmp(&rank(y)).unwrap()
+                })
+                .unwrap();
+            // The planet on the bridge has the heaviest connections
+            assert_eq!(brightest.as_str(), format!("{}1", clique));
+            assert!(members.iter().all(|m| &found[*m] == *brightest));
+        }
+        let names: HashSet<&String> = found.values().collect();
+        assert_eq!(names.len(), 2);
+    }
+
+    #[test]
+    fn lonely_planets_are_their_own_constellation() {
+        let planets = universe(&cliques(), &["x", "y"]);
+        let found = constellations(&planets);
+        assert_eq!(found["x"], "x");
+        assert_eq!(found["y"], "y");
+        let names: HashSet<&String> = found.values().collect();
+        assert_eq!(names.len(), 4);
+    }
+
+    #[test]
+    fn constellations_are_the_same_each_time() {
+        // Ties everywhere: a ring of equal connections can be split in many ways
+        let ring: Vec<(String, String)> = (0..12)
+            .map(|i| (format!("r{:02}", i), format!("r{:02}", (i + 1) % 12)))
+            .collect();
+        let edges: Vec<(&str, &str, i32)> = ring
+            .iter()
+            .map(|(a, b)| (a.as_str(), b.as_str(), 1))
+            .chain(cliques())
+            .collect();
+        // Every HashMap hashes differently, so each run walks the planets in another order
+        let first = constellations(&universe(&edges, &["x"]));
+        for _ in 0..10 {
+            assert_eq!(constellations(&universe(&edges, &["x"])), first);
+        }
+    }
+
+    #[test]
+    fn constellations_are_found_across_galaxies() {
+        let mut c_edges = vec![];
+        let c = ["c1", "c2", "c3", "c4"];
+        for (i, a) in c.iter().enumerate() {
+            for b in c[i + 1..].iter() {
+                c_edges.push((*a, *b, 10));
+            }
+        }
+        // Each galaxy has one clique and half of a third, the bridge between the first two is in both
+        let (mut a_edges, mut b_edges): (Vec<_>, Vec<_>) = cliques()
+            .into_iter()
+            .partition(|(a, _, _)| a.starts_with('a'));
+        b_edges.push(("a1", "b1", 1));
+        for (i, edge) in c_edges.into_iter().enumerate() {
+            if i % 2 == 0 {
+                a_edges.push(edge);
+            } else {
+                b_edges.push(edge);
+            }
+        }
+
+        let found = constellations(&measured(vec![
+            relation("a", &a_edges),
+            relation("b", &b_edges),
+        ]));
+        for clique in ["a", "b", "c"] {
+            let names: HashSet<&String> = found
+                .iter()
+                .filter(|(planet, _)| planet.starts_with(clique))
+                .map(|(_, constellation)| constellation)
+                .collect();
+            assert_eq!(names.len(), 1);
+        }
+        let names: HashSet<&String> = found.values().collect();
+        assert_eq!(names.len(), 3);
+    }
+}
@@ -0,0 +1,424 @@
+use pathfinding::prelude::dijkstra;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    fs::{self, read_dir},
+    io,
+    path::Path,
+};
+
+#[derive(Clone, Serialize, Deserialize, Debug, Eq, Ord, PartialOrd, PartialEq, Hash)]
+pub struct ConnectedTag {
+    // 80% of original json with tag & count
+    #[serde(rename(serialize = "t", deserialize = "t"))]
+    pub name: String,
+    #[serde(rename(serialize = "n", deserialize = "n"))]
+    pub count: i32,
+}
+
+impl ConnectedTag {
+    fn neighbours(
+        &self,
+        relation_map: &HashMap<std::string::String, Vec<ConnectedTag>>,
+    ) -> Vec<(ConnectedTag, i32)> {
+        // Return a list of neighbours from current tag
+        // If tag not in relation hashmap then return empty array
+        match relation_map.get(&self.name) {
+            Some(connected_tags) => connected_tags
+                .iter()
+                .map(|t| (t.clone(), (1000.0 / (t.count as f64)) as i32))
+                .collect(),
+            None => {
+                vec![]
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MetaRelation {
+    // domain on Stack Exchange (e.g. StackOverflow, Unix)
+    pub domain: String,
+    // relation_map where it stores tag -> [tags+counters]
+    pub relation_map: HashMap<String, Vec<ConnectedTag>>,
+}
+
+impl MetaRelation {
+    pub fn new(domain: String, json_path: String) -> Result<MetaRelation, LoadError> {
+        // Instantiate a new Meta Relation with given JSON file
+        let content = fs::read(&json_path).map_err(|source| LoadError::Io {
+            path: json_path.clone(),
+            source,
+        })?;
+        MetaRelation::from_slice(domain, json_path, &content)
+    }
+
+    // Parse the contents of a relation JSON file, path is only used to report errors
+    pub fn from_slice(
+        domain: String,
+        json_path: String,
+        content: &[u8],
+    ) -> Result<MetaRelation, LoadError> {
+        if content.iter().all(|b| b.is_ascii_whitespace()) {
+            return Err(LoadError::Empty { path: json_path });
+        }
+
+        let mut data: HashMap<String, Vec<ConnectedTag>> = serde_json::from_slice(content)
+            .map_err(|source| LoadError::Parse {
+                path: json_path.clone(),
+                source,
+            })?;
+        if data.is_empty() {
+            return Err(LoadError::Empty { path: json_path });
+        }
+        // Sort all tags values during the read so we dont have to sort them again later
+        data.iter_mut().for_each(|(_, tags)| {
+            tags.sort_by(|a, b| {
+                // Sort in descending order
+                a.count.partial_cmp(&b.count).unwrap().reverse()
+            })
+        });
+
+        Ok(MetaRelation {
+            domain,
+            relation_map: data,
+        })
+    }
+
+    pub fn find_top_n(&self, tag_query: &str, n: usize) -> Vec<ConnectedTag> {
+        if self.relation_map.contains_key(tag_query) {
+            let tags = self.relation_map.get(tag_query).unwrap();
+            // Have to return cloned copy here otherwise cannot refer to it with mutex in server module
+            return tags.iter().take(n).cloned().collect::<Vec<ConnectedTag>>();
+        }
+        vec![]
+    }
+
+    pub fn find_path(&self, start: String, goal: String) -> Option<(Vec<ConnectedTag>, i32)> {
+        // Domain name already exists as this function being called from a meta relation instance
+        // Start tag check is done in <ConnectTag>::neighbours()
+        // Check if end tag exists,
+        //  Otherwise the entire relation map will be unnecessary traversed
+        match self.relation_map.get(&goal) {
+            None => None,
+            Some(_) => dijkstra(
+                &ConnectedTag {
+                    name: start,
+                    count: 0,
+                },
+                |t| t.neighbours(&self.relation_map),
+                |t| *t.name == goal,
+            ),
+        }
+    }
+}
+
+// Why a relation file could not be loaded
+#[derive(Debug)]
+pub enum LoadError {
+    Io {
+        path: String,
+        source: io::Error,
+    },
+    Parse {
+        path: String,
+        source: serde_json::Error,
+    },
+    // The file, or the JSON object in it, has nothing in it
+    Empty {
+        path: String,
+    },
+    // Another file already provided this domain
+    DuplicateDomain {
+        domain: String,
+        path: String,
+        loaded_from: String,
+    },
+}
+
+impl LoadError {
+    pub fn path(&self) -> &str {
+        match self {
+            LoadError::Io { path, .. }
+            | LoadError::Parse { path, .. }
+            | LoadError::Empty { path }
+            | LoadError::DuplicateDomain { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io { path, source } => write!(f, "{}: {}", path, source),
+            LoadError::Parse { path, source } => write!(f, "{}: invalid JSON, {}", path, source),
+            LoadError::Empty { path } => write!(f, "{}: no tags in file", path),
+            LoadError::DuplicateDomain {
+                domain,
+                path,
+                loaded_from,
+            } => write!(
+                f,
+                "{}: domain {} already loaded from {}",
+                path, domain, loaded_from
+            ),
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoadError::Io { source, .. } => Some(source),
+            LoadError::Parse { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+// Relations that loaded, and the files that were skipped
+#[derive(Debug, Default)]
+pub struct LoadReport {
+    pub relations: Vec<MetaRelation>,
+    pub errors: Vec<LoadError>,
+    // domain -> file it was loaded from
+    sources: HashMap<String, String>,
+}
+
+// Where relation JSON files are read from
+#[derive(Debug, Clone)]
+pub enum DatasetSource {
+    // Every file in a directory
+    Dir(String),
+    // Only the listed files
+    Files(Vec<String>),
+}
+
+impl Default for DatasetSource {
+    fn default() -> Self {
+        DatasetSource::Dir("datasets/".to_string())
+    }
+}
+
+impl fmt::Display for DatasetSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DatasetSource::Dir(dir) => write!(f, "{}", dir),
+            DatasetSource::Files(paths) => write!(f, "{}", paths.join(", ")),
+        }
+    }
+}
+
+impl DatasetSource {
+    // Every file to load, sorted so duplicate domains are resolved the same way each time
+    pub fn paths(&self) -> Result<Vec<String>, LoadError> {
+        match self {
+            DatasetSource::Dir(dir) => {
+                let to_error = |source| LoadError::Io {
+                    path: dir.clone(),
+                    source,
+                };
+                let mut paths = vec![];
+                for entry in read_dir(dir).map_err(to_error)? {
+                    let entry = entry.map_err(to_error)?;
+                    // Skip hidden files such as .placeholder
+                    if !entry.file_name().to_string_lossy().starts_with('.') {
+                        paths.push(entry.path().to_string_lossy().to_string());
+                    }
+                }
+                paths.sort();
+                Ok(paths)
+            }
+            DatasetSource::Files(paths) => Ok(paths.clone()),
+        }
+    }
+
+    // Load every file, skipping the ones that fail
+    pub fn load(&self) -> LoadReport {
+        let mut report = LoadReport::default();
+        match self.paths() {
+            Ok(paths) => {
+                for path in paths {
+                    report.add(&path, load_relation(&path));
+                }
+            }
+            Err(e) => report.errors.push(e),
+        }
+        report
+    }
+}
+
+impl LoadReport {
+    // Keep a loaded relation unless its domain was already loaded from another file
+    pub fn add(&mut self, path: &str, result: Result<MetaRelation, LoadError>) {
+        let relation = match result {
+            Ok(relation) => relation,
+            Err(e) => {
+                self.errors.push(e);
+                return;
+            }
+        };
+        if let Some(loaded_from) = self.sources.get(&relation.domain) {
+            let e = LoadError::DuplicateDomain {
+                domain: relation.domain,
+                path: path.to_string(),
+                loaded_from: loaded_from.clone(),
+            };
+            self.errors.push(e);
+            return;
+        }
+        self.sources
+            .insert(relation.domain.clone(), path.to_string());
+        self.relations.push(relation);
+    }
+}
+
+// Get filename (exc .json) as domain name
+pub fn domain_of(path: &str) -> String {
+    let filename = Path::new(path)
+        .file_name()
+        .map(|f| f.to_string_lossy().to_string())
+        .unwrap_or_default();
+    filename.split('.').next().unwrap_or_default().to_string()
+}
+
+pub fn load_relation(path: &str) -> Result<MetaRelation, LoadError> {
+    MetaRelation::new(domain_of(path), path.to_string())
+}
+
+// Load all relation JSON files in a directory into memory
+pub fn get_all_relations(relation_dir: &str) -> LoadReport {
+    DatasetSource::Dir(relation_dir.to_string()).load()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    const GOOD: &str =
+        r#"{"bash": [{"t": "zsh", "n": 3}, {"t": "sh", "n": 9}], "zsh": [{"t": "bash", "n": 3}]}"#;
+
+    // A fresh directory for the files of one test
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("sok-cartography-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(dir: &Path, name: &str, content: &str) -> String {
+        let path = dir.join(name);
+        fs::write(&path, content).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn tags_are_sorted_heaviest_first() {
+        let relation =
+            MetaRelation::from_slice("unix".into(), "unix.json".into(), GOOD.as_bytes()).unwrap();
+        let names: Vec<&str> = relation.relation_map["bash"]
+            .iter()
+            .map(|t| t.name.as_str())
+            .collect();
+        assert_eq!(names, ["sh", "zsh"]);
+    }
+
+    #[test]
+    fn empty_files_are_reported() {
+        for content in ["", " \n\t", "{}"] {
+            let e = MetaRelation::from_slice("unix".into(), "unix.json".into(), content.as_bytes())
+                .unwrap_err();
+            assert!(matches!(&e, LoadError::Empty { path } if path == "unix.json"));
+            assert_eq!(e.to_string(), "unix.json: no tags in file");
+        }
+    }
+
+    #[test]
+    fn malformed_json_is_reported() {
+        for content in [r#"{"bash": ["#, "[1, 2]", r#"{"bash": [{"t": "zsh"}]}"#] {
+            let e = MetaRelation::from_slice("unix".into(), "unix.json".into(), content.as_bytes())
+                .unwrap_err();
+            assert!(matches!(&e, LoadError::Parse { path, .. } if path == "unix.json"));
+            assert!(e.to_string().starts_with("unix.json: invalid JSON"));
+            assert!(e.source().is_some());
+        }
+    }
+
+    #[test]
+    fn duplicate_domains_keep_the_first_file() {
+        let first = scratch_dir("first");
+        let second = scratch_dir("second");
+        let files = vec![
+            write(&first, "unix.json", GOOD),
+            write(&second, "unix.json", GOOD),
+        ];
+        let report = DatasetSource::Files(files.clone()).load();
+
+        assert_eq!(report.relations.len(), 1);
+        assert_eq!(report.relations[0].domain, "unix");
+        assert_eq!(report.errors.len(), 1);
+        match &report.errors[0] {
+            LoadError::DuplicateDomain {
+                domain,
+                path,
+                loaded_from,
+            } => {
+                assert_eq!(domain, "unix");
+                assert_eq!(path, &files[1]);
+                assert_eq!(loaded_from, &files[0]);
+            }
+            e => panic!("expected a duplicate domain, got {}", e),
+        }
+        fs::remove_dir_all(first).unwrap();
+        fs::remove_dir_all(second).unwrap();
+    }
+
+    #[test]
+    fn bad_files_in_a_directory_are_skipped() {
+        let dir = scratch_dir("mixed");
+        let unix = write(&dir, "unix.json", GOOD);
+        let empty = write(&dir, "empty.json", "");
+        let broken = write(&dir, "broken.json", "{not json");
+        let superuser = write(&dir, "superuser.stackexchange.json", GOOD);
+        write(&dir, ".placeholder", "");
+        let missing = dir.join("missing.json").to_string_lossy().to_string();
+
+        let source = DatasetSource::Dir(dir.to_string_lossy().to_string());
+        assert_eq!(
+            source.paths().unwrap(),
+            [broken.clone(), empty.clone(), superuser, unix]
+        );
+        let mut report = source.load();
+        report.add(&missing, load_relation(&missing));
+
+        let domains: Vec<&str> = report.relations.iter().map(|r| r.domain.as_str()).collect();
+        assert_eq!(domains, ["superuser", "unix"]);
+        let errors: Vec<&str> = report.errors.iter().map(|e| e.path()).collect();
+        assert_eq!(errors, [broken.as_str(), empty.as_str(), missing.as_str()]);
+        assert!(matches!(report.errors[0], LoadError::Parse { .. }));
+        assert!(matches!(report.errors[1], LoadError::Empty { .. }));
+        assert!(matches!(report.errors[2], LoadError::Io { .. }));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn missing_directories_are_reported() {
+        let dir = scratch_dir("gone");
+        fs::remove_dir_all(&dir).unwrap();
+        let report = DatasetSource::Dir(dir.to_string_lossy().to_string()).load();
+        assert!(report.relations.is_empty());
+        assert_eq!(report.errors.len(), 1);
+        assert!(matches!(report.errors[0], LoadError::Io { .. }));
+    }
+
+    #[test]
+    fn domains_are_file_names_up_to_the_first_dot() {
+        assert_eq!(domain_of("datasets/unix.json"), "unix");
+        assert_eq!(domain_of("superuser.stackexchange.json"), "superuser");
+    }
+}
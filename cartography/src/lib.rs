@@ -0,0 +1,39 @@
+//! Tag relations of Stack Exchange sites and the universe built from them,
+//! without any rendering. Used by the `sok` app and usable from other tools.
+//!
+//! ```no_run
+//! use sok_cartography::{generate_universe_cartography, DatasetSource};
+//!
+//! let (galaxies, planets, errors) = generate_universe_cartography(&DatasetSource::default());
+//! for e in errors {
+//!     eprintln!("Skipped {}", e);
+//! }
+//! let unix = &galaxies["unix"];
+//! println!("{:?}", unix.relation.find_top_n("bash", 5));
+//! println!("{:?}", unix.relation.find_path("bash".into(), "zsh".into()));
+//! ```
+//!
+//! Centrality and constellations take longer to work out, so they are only measured on request:
+//!
+//! ```no_run
+//! use sok_cartography::{find_constellations, generate_universe_cartography, measure_centrality};
+//! # let (galaxies, mut planets, _) = generate_universe_cartography(&Default::default());
+//! measure_centrality(&galaxies, &mut planets);
+//! find_constellations(&galaxies, &mut planets);
+//! println!("{:?}", planets["bash"].constellation);
+//! ```
+pub mod analysis;
+pub mod constellation;
+pub mod data;
+pub mod universe;
+
+pub use analysis::{
+    measure_centrality, universe_connections, universe_stats, Centrality, Distribution,
+    GalaxyStats, GraphStats, UniverseStats,
+};
+pub use constellation::find_constellations;
+pub use data::{
+    domain_of, get_all_relations, load_relation, ConnectedTag, DatasetSource, LoadError,
+    LoadReport, MetaRelation,
+};
+pub use universe::{build_universe, generate_universe_cartography, Connection, Galaxy, Planet};
@@ -0,0 +1,185 @@
+use crate::{
+    analysis::Centrality,
+    data::{DatasetSource, LoadError, MetaRelation},
+};
+use std::collections::{HashMap, HashSet};
+
+// Describe a single connection between two planets
+#[derive(Debug)]
+pub struct Connection {
+    pub planet_pairs: (String, String),
+    pub count: i32,
+    // Galaxy whose tags made the connection
+    pub galaxy: String,
+}
+
+// Describe a planet and its connections
+#[derive(Debug)]
+pub struct Planet {
+    pub name: String,
+    pub conns: Vec<Connection>,
+    // One planet can belong to multiple galaxies
+    pub belong_galaxy: HashSet<String>,
+    // Set by measure_centrality, which is too slow to run for every universe
+    pub centrality: Option<Centrality>,
+    // Set by find_constellations, named after the most central planet in it
+    pub constellation: Option<String>,
+}
+
+// Describe the set (e.g. Stack Overflow, Ask Ubuntu) of a bunch of planets
+pub struct Galaxy {
+    pub name: String,
+    // Tag relations of the domain, kept around for queries
+    pub relation: MetaRelation,
+}
+
+// Load every dataset and build the universe from the ones that loaded,
+//  also returning why the others were skipped
+pub fn generate_universe_cartography(
+    source: &DatasetSource,
+) -> (
+    HashMap<String, Galaxy>,
+    HashMap<String, Planet>,
+    Vec<LoadError>,
+) {
+    let report = source.load();
+    let (galaxies, planets) = build_universe(report.relations);
+    (galaxies, planets, report.errors)
+}
+
+pub fn build_universe(
+    meta_relations: Vec<MetaRelation>,
+) -> (HashMap<String, Galaxy>, HashMap<String, Planet>) {
+    // Relations of every domain, each becomes a galaxy
+
+    // Discover all available planets across each galaxies into a queue
+    let mut planets: HashMap<String, Planet> = HashMap::new();
+    let mut discovered_planets: HashSet<String> = HashSet::new();
+
+    for meta in meta_relations.iter() {
+        for planet_name in meta.relation_map.keys() {
+            if !discovered_planets.contains(planet_name) {
+                discovered_planets.insert(planet_name.clone());
+
+                planets.insert(
+                    planet_name.clone(),
+                    Planet {
+                        name: planet_name.to_string(),
+                        conns: vec![],
+                        belong_galaxy: HashSet::new(),
+                        centrality: None,
+                        constellation: None,
+                    },
+                );
+            }
+            // Tags shared between domains make the planet part of every one of those galaxies
+            let p = planets.get_mut(planet_name).unwrap();
+            p.belong_galaxy.insert(meta.domain.clone());
+        }
+    }
+
+    for meta in meta_relations.iter() {
+        // As planetary connections are bi-directional,
+        //   need to filter out explored planets.
+        //   Each galaxy connects its own planets, even ones another galaxy connects too
+        let mut explored_planets: HashSet<String> = HashSet::new();
+        // create connections from tags
+        for (planet_name, connected_tags) in meta.relation_map.iter() {
+            let p = planets.get_mut(planet_name).unwrap();
+            for t in connected_tags.iter() {
+                // check if target planet has already been explored and skip accordingly
+                if explored_planets.contains(&t.name) {
+                    continue;
+                }
+                let conn = Connection {
+                    planet_pairs: (planet_name.to_string(), t.name.clone()),
+                    count: t.count,
+                    galaxy: meta.domain.clone(),
+                };
+                p.conns.push(conn);
+            }
+            explored_planets.insert(planet_name.to_string());
+        }
+    }
+
+    // Generate Galaxies
+    let galaxies: HashMap<String, Galaxy> = meta_relations
+        .into_iter()
+        .map(|meta| {
+            (
+                meta.domain.clone(),
+                Galaxy {
+                    name: meta.domain.clone(),
+                    relation: meta,
+                },
+            )
+        })
+        .collect();
+
+    // for (_, p) in planets.iter() {
+    //     println!("[PLANET] {}", p.name);
+    //     println!("[HOME GALAXY] {:?}", p.belong_galaxy);
+    //     println!("[CONNS] {:?}", p.conns);
+    // }
+
+    (galaxies, planets)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::data::ConnectedTag;
+
+    // Relation of a domain with tags connected both ways
+    pub(crate) fn relation(domain: &str, edges: &[(&str, &str, i32)]) -> MetaRelation {
+        let mut relation_map: HashMap<String, Vec<ConnectedTag>> = HashMap::new();
+        for (a, b, count) in edges {
+            for (from, to) in [(a, b), (b, a)] {
+                relation_map
+                    .entry(from.to_string())
+                    .or_default()
+                    .push(ConnectedTag {
+                        name: to.to_string(),
+                        count: *count,
+                    });
+            }
+        }
+        MetaRelation {
+            domain: domain.to_string(),
+            relation_map,
+        }
+    }
+
+    #[test]
+    fn overlapping_galaxies_each_connect_their_planets() {
+        // Every HashMap hashes differently, so each run walks the planets in another order
+        for _ in 0..20 {
+            let (_, planets) = build_universe(vec![
+                relation("a", &[("x", "y", 10)]),
+                relation("b", &[("x", "y", 20), ("x", "z", 5)]),
+            ]);
+            let conns: BTreeSet<(String, String, String, i32)> = planets
+                .values()
+                .flat_map(|planet| planet.conns.iter())
+                .map(|conn| {
+                    let (a, b) = conn.planet_pairs.clone();
+                    let (a, b) = if a <= b { (a, b) } else { (b, a) };
+                    (conn.galaxy.clone(), a, b, conn.count)
+                })
+                .collect();
+            let expected: BTreeSet<(String, String, String, i32)> =
+                [("a", "x", "y", 10), ("b", "x", "y", 20), ("b", "x", "z", 5)]
+                    .iter()
+                    .map(|(g, a, b, count)| (g.to_string(), a.to_string(), b.to_string(), *count))
+                    .collect();
+            assert_eq!(conns, expected);
+            // Listed from both ends, but connected once per galaxy
+            let total: usize = planets.values().map(|planet| planet.conns.len()).sum();
+            assert_eq!(total, 3);
+            assert_eq!(planets["x"].belong_galaxy.len(), 2);
+            assert_eq!(planets["z"].belong_galaxy.len(), 1);
+        }
+    }
+}
@@ -0,0 +1,184 @@
+// named camera viewpoints, saved next to the layout cache so they can be shared
+use std::{collections::BTreeMap, fs};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    camera::{MouseState, MoveCamera, SceneCam},
+    console::{AddConsoleCommand, Arg, CommandEvent, CommandSpec, ConsoleLog},
+    controls::{Action, Actions},
+    layout::{write_cache_file, CACHE_DIR},
+    scene::Selection,
+};
+
+const BOOKMARKS_FILE: &str = "bookmarks.ron";
+// Bookmark used by the quick save/recall keys
+const QUICK_BOOKMARK: &str = "quick";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub position: [f32; 3],
+    pub yaw: f32,
+    pub pitch: f32,
+    pub selected: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Bookmarks {
+    pub views: BTreeMap<String, Bookmark>,
+}
+
+impl Bookmarks {
+    fn path() -> String {
+        format!("{}{}", CACHE_DIR, BOOKMARKS_FILE)
+    }
+
+    fn load() -> Bookmarks {
+        match fs::read_to_string(Bookmarks::path()) {
+            Ok(content) => ron::from_str(&content).unwrap_or_else(|e| {
+                println!("[BOOKMARK] Ignoring broken {}: {}", Bookmarks::path(), e);
+                Bookmarks::default()
+            }),
+            Err(_) => Bookmarks::default(),
+        }
+    }
+
+    fn save(&self, log: &mut ConsoleLog) {
+        if let Err(e) = write_cache_file(&Bookmarks::path(), self) {
+            log.error(format!(
+                "[BOOKMARK] Failed to write {}: {}",
+                Bookmarks::path(),
+                e
+            ));
+        }
+    }
+}
+
+// What to do with a bookmark, from either a key press or the console
+enum BookmarkRequest {
+    Save(String),
+    Recall(String),
+    Delete(String),
+    List,
+}
+
+pub struct BookmarkPlugin;
+
+impl Plugin for BookmarkPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Bookmarks::load())
+            .add_event::<BookmarkRequest>()
+            .add_console_command(
+                CommandSpec::new("bookmark")
+                    .usage(&[], "List saved views")
+                    .usage(&[Arg::Literal("list")], "List saved views")
+                    .usage(
+                        &[Arg::Literal("save"), Arg::Text("name")],
+                        "Save the current view",
+                    )
+                    .usage(
+                        &[Arg::Literal("go"), Arg::Text("name")],
+                        "Return to a saved view",
+                    )
+                    .usage(
+                        &[Arg::Literal("delete"), Arg::Text("name")],
+                        "Delete a saved view",
+                    ),
+            )
+            .add_system(bookmark_control)
+            .add_system(bookmark_command)
+            .add_system(handle_bookmarks);
+    }
+}
+
+fn bookmark_control(actions: Res<Actions>, mut requests: EventWriter<BookmarkRequest>) {
+    if actions.just_pressed(Action::SaveBookmark) {
+        requests.send(BookmarkRequest::Save(QUICK_BOOKMARK.to_string()));
+    }
+    if actions.just_pressed(Action::RecallBookmark) {
+        requests.send(BookmarkRequest::Recall(QUICK_BOOKMARK.to_string()));
+    }
+}
+
+// bookmark save|go|delete <name>
+// bookmark list
+fn bookmark_command(
+    mut events: EventReader<CommandEvent>,
+    mut requests: EventWriter<BookmarkRequest>,
+) {
+    for ev in events.iter().filter(|ev| ev.name == "bookmark") {
+        let args: Vec<&str> = ev.args.iter().map(|a| a.as_str()).collect();
+        match args.as_slice() {
+            ["save", name] => requests.send(BookmarkRequest::Save(name.to_string())),
+            ["go", name] => requests.send(BookmarkRequest::Recall(name.to_string())),
+            ["delete", name] => requests.send(BookmarkRequest::Delete(name.to_string())),
+            [] | ["list"] => requests.send(BookmarkRequest::List),
+            _ => {}
+        }
+    }
+}
+
+fn handle_bookmarks(
+    mut requests: EventReader<BookmarkRequest>,
+    mut bookmarks: ResMut<Bookmarks>,
+    mut selection: ResMut<Selection>,
+    mut move_evw: EventWriter<MoveCamera>,
+    mut log: ResMut<ConsoleLog>,
+    mouse_state: Res<MouseState>,
+    camera_q: Query<&Transform, With<SceneCam>>,
+) {
+    let cam_tf = camera_q.single();
+
+    for request in requests.iter() {
+        match request {
+            BookmarkRequest::Save(name) => {
+                let bookmark = Bookmark {
+                    position: cam_tf.translation.into(),
+                    yaw: mouse_state.yaw,
+                    pitch: mouse_state.pitch,
+                    selected: selection.planet.clone(),
+                };
+                bookmarks.views.insert(name.clone(), bookmark);
+                bookmarks.save(&mut log);
+                log.info(format!("[BOOKMARK] Saved {}", name));
+            }
+            BookmarkRequest::Recall(name) => match bookmarks.views.get(name) {
+                Some(bookmark) => {
+                    move_evw.send(MoveCamera {
+                        position: bookmark.position.into(),
+                        yaw: bookmark.yaw,
+                        pitch: bookmark.pitch,
+                        duration: 0.,
+                    });
+                    selection.planet = bookmark.selected.clone();
+                }
+                None => log.error(format!("[BOOKMARK] No bookmark named {}", name)),
+            },
+            BookmarkRequest::Delete(name) => {
+                if bookmarks.views.remove(name).is_some() {
+                    bookmarks.save(&mut log);
+                    log.info(format!("[BOOKMARK] Deleted {}", name));
+                } else {
+                    log.error(format!("[BOOKMARK] No bookmark named {}", name));
+                }
+            }
+            BookmarkRequest::List => {
+                if bookmarks.views.is_empty() {
+                    log.info("[BOOKMARK] No saved views");
+                }
+                for (name, bookmark) in bookmarks.views.iter() {
+                    let selected = bookmark.selected.as_deref().unwrap_or("-");
+                    log.info(format!(
+                        "[BOOKMARK] {} @ ({:.0}, {:.0}, {:.0}) selected: {}",
+                        name,
+                        bookmark.position[0],
+                        bookmark.position[1],
+                        bookmark.position[2],
+                        selected
+                    ));
+                }
+            }
+        }
+    }
+}
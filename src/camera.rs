@@ -1,28 +1,159 @@
-use bevy::{input::mouse::MouseMotion, prelude::*};
+use bevy::{
+    input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
+    prelude::*,
+};
+
+use crate::{
+    console::{AddConsoleCommand, Arg, CommandEvent, CommandSpec, ConsoleLog},
+    controls::{Action, Actions, Bindings},
+    cvar::{AddCvar, Cvar},
+    debug::ConsoleState,
+    scene::{CartographyRes, PlanetComp, PlanetRadius},
+};
 
 const CAMERA_MOVE_SPEED: f32 = 700.;
+const CAMERA_BOOST_MULTIPLIER: f32 = 4.;
+// How quickly velocity catches up with input, and decays without it (per second)
+const CAMERA_ACCELERATION: f32 = 6.;
+const CAMERA_DAMPING: f32 = 4.;
+// Each scroll notch multiplies or divides speed by this much
+const CAMERA_SPEED_STEP: f32 = 1.15;
+const CAMERA_SPEED_SCALE_MIN: f32 = 0.1;
+const CAMERA_SPEED_SCALE_MAX: f32 = 10.;
+// Speed is unscaled at this distance from the nearest planet,
+//  slower when closer and faster when further away
+const CAMERA_SPEED_REFERENCE_DISTANCE: f32 = 1000.;
+const CAMERA_DISTANCE_FACTOR_MIN: f32 = 0.1;
+const CAMERA_DISTANCE_FACTOR_MAX: f32 = 4.;
 const CAMERA_SENSITIVITY: f32 = 0.00012;
-const CAMERA_RENDER_DISTANCE: f32 = 10000.0;
+// Far enough to keep the whole universe in view when it is framed
+const CAMERA_RENDER_DISTANCE: f32 = 30000.0;
+// Extra room around framed planets so they do not touch the screen edges
+const CAMERA_FRAME_MARGIN: f32 = 1.1;
 
 pub struct CameraPlugin;
 
+pub struct CameraMoveSpeed(pub f32);
+
+impl Cvar for CameraMoveSpeed {
+    const NAME: &'static str = "camera_move_speed";
+    const HELP: &'static str = "Base flying speed of the camera";
+    const DEFAULT: f32 = CAMERA_MOVE_SPEED;
+
+    fn new(value: f32) -> Self {
+        Self(value)
+    }
+
+    fn get(&self) -> f32 {
+        self.0
+    }
+}
+
+pub struct CameraSensitivity(pub f32);
+
+impl Cvar for CameraSensitivity {
+    const NAME: &'static str = "camera_sensitivity";
+    const HELP: &'static str = "How far the camera turns per pixel of mouse movement";
+    const DEFAULT: f32 = CAMERA_SENSITIVITY;
+
+    fn new(value: f32) -> Self {
+        Self(value)
+    }
+
+    fn get(&self) -> f32 {
+        self.0
+    }
+}
+
 #[derive(Default)]
-struct MouseState {
-    pitch: f32,
-    yaw: f32,
+pub struct MouseState {
+    pub pitch: f32,
+    pub yaw: f32,
+}
+
+impl MouseState {
+    pub fn rotation(&self) -> Quat {
+        // Order is important to prevent unintended roll
+        Quat::from_axis_angle(Vec3::Y, self.yaw) * Quat::from_axis_angle(Vec3::X, self.pitch)
+    }
+}
+
+struct CameraMotion {
+    velocity: Vec3,
+    // User controlled speed multiplier, adjusted with the scroll wheel
+    speed_scale: f32,
+}
+
+impl Default for CameraMotion {
+    fn default() -> Self {
+        Self {
+            velocity: Vec3::ZERO,
+            speed_scale: 1.,
+        }
+    }
 }
 
 #[derive(Component)]
 pub struct SceneCam;
 
+// Move the camera to a viewpoint, e.g. when recalling a bookmark.
+//  A zero duration jumps there, otherwise the camera flies there smoothly
+#[derive(Debug, Clone)]
+pub struct MoveCamera {
+    pub position: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub duration: f32,
+}
+
+// A smooth flight in progress between two viewpoints
+pub(crate) struct CameraFlight {
+    from: (Vec3, f32, f32),
+    to: MoveCamera,
+    elapsed: f32,
+}
+
+// Move the camera back along its view direction until the requested planets fit on screen
+#[derive(Debug, Clone)]
+pub enum FrameRequest {
+    All,
+    Galaxy(String),
+    Constellation(String),
+}
+
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(MouseState::default())
+            .insert_resource(CameraMotion::default())
+            .insert_resource(None::<CameraFlight>)
+            .add_cvar::<CameraMoveSpeed>()
+            .add_cvar::<CameraSensitivity>()
+            .add_event::<FrameRequest>()
+            .add_event::<MoveCamera>()
+            .add_console_command(
+                CommandSpec::new("frame")
+                    .usage(&[], "Fit the whole universe on screen")
+                    .usage(&[Arg::Literal("all")], "Fit the whole universe on screen")
+                    .usage(
+                        &[Arg::Literal("galaxy"), Arg::Text("name")],
+                        "Fit a single galaxy on screen",
+                    )
+                    .usage(
+                        &[Arg::Literal("constellation"), Arg::Text("name")],
+                        "Fit a single constellation on screen",
+                    ),
+            )
             .add_startup_system(setup_cursor_lock.system())
             .add_startup_system(setup_camera.system())
+            .add_system(frame_new_planets.system())
+            .add_system(frame_control.system())
+            .add_system(frame_command.system())
+            .add_system(frame_camera.system())
+            .add_system(move_camera.system())
+            .add_system(fly_camera.system())
+            .add_system(camera_speed_control.system())
             .add_system(camera_movement.system())
-            .add_system(exit_control.system())
-            .add_system(camera_mouse_movement.system());
+            .add_system(camera_look.system());
     }
 }
 
@@ -34,12 +165,101 @@ fn setup_camera(mut commands: Commands) {
                 far: CAMERA_RENDER_DISTANCE,
                 ..Default::default()
             },
-            transform: Transform::from_xyz(0., 0., 0.).looking_at(Vec3::ZERO, Vec3::X),
+            // Start unrotated to match the zero yaw & pitch in MouseState
+            transform: Transform::identity(),
             ..Default::default()
         })
         .insert(SceneCam);
 }
 
+// Frame the universe once its first planets have been spawned,
+//  planets added by reloading datasets later keep the camera where it is
+fn frame_new_planets(
+    added_q: Query<(), Added<PlanetComp>>,
+    mut framed: Local<bool>,
+    mut frame_evw: EventWriter<FrameRequest>,
+) {
+    if !*framed && !added_q.is_empty() {
+        frame_evw.send(FrameRequest::All);
+        *framed = true;
+    }
+}
+
+fn frame_control(actions: Res<Actions>, mut frame_evw: EventWriter<FrameRequest>) {
+    if actions.just_pressed(Action::FrameAll) {
+        frame_evw.send(FrameRequest::All);
+    }
+}
+
+// frame [all]
+// frame galaxy <name>
+// frame constellation <name>
+fn frame_command(mut events: EventReader<CommandEvent>, mut frame_evw: EventWriter<FrameRequest>) {
+    for ev in events.iter().filter(|ev| ev.name == "frame") {
+        let args: Vec<&str> = ev.args.iter().map(|a| a.as_str()).collect();
+        match args.as_slice() {
+            [] | ["all"] => frame_evw.send(FrameRequest::All),
+            ["galaxy", name] => frame_evw.send(FrameRequest::Galaxy(name.to_string())),
+            ["constellation", name] => {
+                frame_evw.send(FrameRequest::Constellation(name.to_string()))
+            }
+            _ => {}
+        }
+    }
+}
+
+fn frame_camera(
+    mut frame_evr: EventReader<FrameRequest>,
+    mut log: ResMut<ConsoleLog>,
+    mut motion: ResMut<CameraMotion>,
+    planet_radius: Res<PlanetRadius>,
+    cartography: Res<CartographyRes>,
+    mut camera_q: Query<(&mut Transform, &PerspectiveProjection), With<SceneCam>>,
+    planet_q: Query<(&PlanetComp, &Transform), Without<SceneCam>>,
+) {
+    let (mut cam_tf, projection) = camera_q.single_mut();
+
+    for request in frame_evr.iter() {
+        let positions: Vec<Vec3> = planet_q
+            .iter()
+            .filter(|(planet, _)| match request {
+                FrameRequest::All => true,
+                FrameRequest::Galaxy(name) => planet.galaxies.contains(name),
+                FrameRequest::Constellation(name) => matches!(
+                    cartography.planets.get(&planet.name),
+                    Some(p) if p.constellation.as_ref() == Some(name)
+                ),
+            })
+            .map(|(_, planet_tf)| planet_tf.translation)
+            .collect();
+        if positions.is_empty() {
+            log.warn(format!("[CAMERA] No planets to frame for {:?}", request));
+            continue;
+        }
+
+        // Bounding sphere around the centre of the planets' bounding box
+        let (min, max) = positions
+            .iter()
+            .fold((positions[0], positions[0]), |(min, max), p| {
+                (min.min(*p), max.max(*p))
+            });
+        let center = (min + max) / 2.;
+        let radius = positions
+            .iter()
+            .map(|p| p.distance(center))
+            .fold(0., f32::max)
+            + planet_radius.0;
+
+        // Fit the sphere into the narrower of the vertical and horizontal field of view
+        let half_fov_y = projection.fov / 2.;
+        let half_fov_x = (half_fov_y.tan() * projection.aspect_ratio).atan();
+        let distance = radius * CAMERA_FRAME_MARGIN / half_fov_y.min(half_fov_x).sin();
+
+        cam_tf.translation = center - cam_tf.forward() * distance;
+        motion.velocity = Vec3::ZERO;
+    }
+}
+
 // grab & lock cursor when game first starts
 fn setup_cursor_lock(mut windows: ResMut<Windows>) {
     let window = windows.get_primary_mut().unwrap();
@@ -47,11 +267,122 @@ fn setup_cursor_lock(mut windows: ResMut<Windows>) {
     window.set_cursor_visibility(!window.cursor_visible());
 }
 
+// Yaw & pitch that make the camera look along the given direction
+pub fn look_angles(direction: Vec3) -> (f32, f32) {
+    let direction = direction.normalize_or_zero();
+    let yaw = (-direction.x).atan2(-direction.z);
+    let pitch = direction.y.clamp(-1., 1.).asin();
+    (yaw, pitch)
+}
+
+fn move_camera(
+    mut move_evr: EventReader<MoveCamera>,
+    mut state: ResMut<MouseState>,
+    mut motion: ResMut<CameraMotion>,
+    mut flight: ResMut<Option<CameraFlight>>,
+    mut query: Query<&mut Transform, With<SceneCam>>,
+) {
+    let mut cam_tf = query.single_mut();
+    for ev in move_evr.iter() {
+        motion.velocity = Vec3::ZERO;
+        if ev.duration > 0. {
+            *flight = Some(CameraFlight {
+                from: (cam_tf.translation, state.yaw, state.pitch),
+                to: ev.clone(),
+                elapsed: 0.,
+            });
+            continue;
+        }
+        *flight = None;
+        state.yaw = ev.yaw;
+        state.pitch = ev.pitch;
+        cam_tf.translation = ev.position;
+        cam_tf.rotation = state.rotation();
+    }
+}
+
+fn fly_camera(
+    time: Res<Time>,
+    mut state: ResMut<MouseState>,
+    mut flight: ResMut<Option<CameraFlight>>,
+    mut query: Query<&mut Transform, With<SceneCam>>,
+) {
+    let mut cam_tf = query.single_mut();
+    let done = match flight.as_mut() {
+        Some(flight) => {
+            flight.elapsed += time.delta_seconds();
+            let t = (flight.elapsed / flight.to.duration).min(1.);
+            // Ease in and out so flights start and stop gently
+            let t = t * t * (3. - 2. * t);
+
+            let (from_position, from_yaw, from_pitch) = flight.from;
+            // Turn the short way round instead of spinning through a full circle
+            let mut yaw_delta = (flight.to.yaw - from_yaw) % std::f32::consts::TAU;
+            if yaw_delta > std::f32::consts::PI {
+                yaw_delta -= std::f32::consts::TAU;
+            } else if yaw_delta < -std::f32::consts::PI {
+                yaw_delta += std::f32::consts::TAU;
+            }
+
+            state.yaw = from_yaw + yaw_delta * t;
+            state.pitch = from_pitch + (flight.to.pitch - from_pitch) * t;
+            cam_tf.translation = from_position.lerp(flight.to.position, t);
+            cam_tf.rotation = state.rotation();
+            t >= 1.
+        }
+        None => false,
+    };
+    if done {
+        *flight = None;
+    }
+}
+
+// Scroll wheel (or the speed actions) scale the free-fly speed up and down
+fn camera_speed_control(
+    actions: Res<Actions>,
+    windows: Res<Windows>,
+    console_state: Res<State<ConsoleState>>,
+    mut wheel_evr: EventReader<MouseWheel>,
+    mut motion: ResMut<CameraMotion>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    let window = windows.get_primary().unwrap();
+    // The wheel scrolls the console output while it is open,
+    //  and like looking around, only steers the camera while it holds the cursor
+    let steering = *console_state.current() == ConsoleState::Off
+        && window.cursor_locked()
+        && window.is_focused();
+    let mut steps: f32 = wheel_evr
+        .iter()
+        .filter(|_| steering)
+        .map(|ev| match ev.unit {
+            MouseScrollUnit::Line => ev.y,
+            // Touchpads report pixels, roughly 20 of them per notch
+            MouseScrollUnit::Pixel => ev.y / 20.,
+        })
+        .sum();
+    if actions.just_pressed(Action::SpeedUp) {
+        steps += 1.;
+    }
+    if actions.just_pressed(Action::SpeedDown) {
+        steps -= 1.;
+    }
+
+    if steps != 0. {
+        motion.speed_scale = (motion.speed_scale * CAMERA_SPEED_STEP.powf(steps))
+            .clamp(CAMERA_SPEED_SCALE_MIN, CAMERA_SPEED_SCALE_MAX);
+        log.info(format!("[CAMERA] Speed x{:.2}", motion.speed_scale));
+    }
+}
+
 fn camera_movement(
     time: Res<Time>,
-    input: Res<Input<KeyCode>>,
+    actions: Res<Actions>,
     windows: Res<Windows>,
+    move_speed: Res<CameraMoveSpeed>,
+    mut motion: ResMut<CameraMotion>,
     mut query: Query<&mut Transform, With<SceneCam>>,
+    planet_q: Query<&Transform, (With<PlanetComp>, Without<SceneCam>)>,
 ) {
     let mut cam_tf = query.single_mut();
     let window = windows.get_primary().unwrap();
@@ -60,33 +391,56 @@ fn camera_movement(
     let forward = -Vec3::new(local_z.x, 0., local_z.z);
     let right = Vec3::new(local_z.z, 0., -local_z.x);
 
-    for key in input.get_pressed() {
-        if window.cursor_locked() {
-            match key {
-                KeyCode::W => v += forward,
-                KeyCode::S => v -= forward,
-                KeyCode::A => v -= right,
-                KeyCode::D => v += right,
-                KeyCode::E => v += Vec3::Y,
-                KeyCode::Q => v -= Vec3::Y,
-                _ => (),
-            }
+    let directions = [
+        (Action::MoveForward, forward),
+        (Action::MoveBackward, -forward),
+        (Action::MoveLeft, -right),
+        (Action::MoveRight, right),
+        (Action::MoveUp, Vec3::Y),
+        (Action::MoveDown, -Vec3::Y),
+    ];
+    if window.cursor_locked() {
+        for (action, direction) in directions {
+            v += direction * actions.value(action);
         }
     }
 
-    v = v.normalize_or_zero();
-    cam_tf.translation += v * time.delta_seconds() * CAMERA_MOVE_SPEED;
-}
+    // Analog sticks can ask for less than full speed, but never more
+    v = v.clamp_length_max(1.);
 
-fn exit_control(input: Res<Input<KeyCode>>) {
-    if input.just_pressed(KeyCode::Escape) {
-        std::process::exit(0);
+    // Slow down near planets for precision, speed up when crossing empty space
+    let nearest_planet = planet_q
+        .iter()
+        .map(|planet_tf| planet_tf.translation.distance(cam_tf.translation))
+        .fold(f32::INFINITY, f32::min);
+    let distance_factor = (nearest_planet / CAMERA_SPEED_REFERENCE_DISTANCE)
+        .clamp(CAMERA_DISTANCE_FACTOR_MIN, CAMERA_DISTANCE_FACTOR_MAX);
+
+    let mut speed = move_speed.0 * motion.speed_scale * distance_factor;
+    if actions.pressed(Action::Boost) {
+        speed *= CAMERA_BOOST_MULTIPLIER;
     }
+
+    // Ease towards the requested velocity, and coast to a stop once input is released
+    let dt = time.delta_seconds();
+    let target = v * speed;
+    let rate = if target == Vec3::ZERO {
+        CAMERA_DAMPING
+    } else {
+        CAMERA_ACCELERATION
+    };
+    motion.velocity = motion.velocity.lerp(target, 1. - (-rate * dt).exp());
+    cam_tf.translation += motion.velocity * dt;
 }
 
-// Handles looking around if cursor is locked
-fn camera_mouse_movement(
+// Handles looking around with the mouse or a gamepad stick if cursor is locked
+#[allow(clippy::too_many_arguments)]
+fn camera_look(
+    time: Res<Time>,
     windows: Res<Windows>,
+    actions: Res<Actions>,
+    bindings: Res<Bindings>,
+    sensitivity: Res<CameraSensitivity>,
     mut motion_evr: EventReader<MouseMotion>,
     mut state: ResMut<MouseState>,
     mut query: Query<&mut Transform, With<SceneCam>>,
@@ -94,18 +448,30 @@ fn camera_mouse_movement(
     let window = windows.get_primary().unwrap();
     let mut camera_tf = query.single_mut();
 
+    if !window.cursor_locked() || !window.is_focused() {
+        return;
+    }
+
+    // Using smallest of height or width ensures equal vertical and horizontal sensitivity
+    let window_scale = window.height().min(window.width());
+    let mut delta = Vec2::ZERO;
     for ev in motion_evr.iter() {
-        if window.cursor_locked() && window.is_focused() {
-            // Using smallest of height or width ensures equal vertical and horizontal sensitivity
-            let window_scale = window.height().min(window.width());
+        delta += ev.delta * (sensitivity.0 * window_scale).to_radians();
+    }
 
-            state.pitch -= (CAMERA_SENSITIVITY * ev.delta.y * window_scale).to_radians();
-            state.yaw -= (CAMERA_SENSITIVITY * ev.delta.x * window_scale).to_radians();
-            state.pitch = state.pitch.clamp(-1.54, 1.54);
+    // Stick deflection is a rate, so scale it by frame time unlike mouse motion
+    let stick = Vec2::new(
+        actions.value(Action::LookRight) - actions.value(Action::LookLeft),
+        actions.value(Action::LookDown) - actions.value(Action::LookUp),
+    );
+    delta += stick * bindings.gamepad.look_sensitivity * time.delta_seconds();
 
-            // Order is important to prevent unintended roll
-            camera_tf.rotation = Quat::from_axis_angle(Vec3::Y, state.yaw)
-                * Quat::from_axis_angle(Vec3::X, state.pitch);
-        }
+    if delta == Vec2::ZERO {
+        return;
     }
+    state.pitch -= delta.y;
+    state.yaw -= delta.x;
+    state.pitch = state.pitch.clamp(-1.54, 1.54);
+
+    camera_tf.rotation = state.rotation();
 }
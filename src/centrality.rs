@@ -0,0 +1,323 @@
+// how central planets are: shown for the selected planet and used to size, shade and label them
+use std::sync::{
+    mpsc::{channel, Receiver},
+    Mutex,
+};
+
+use bevy::{prelude::*, tasks::AsyncComputeTaskPool};
+use sok_cartography::constellation::find_constellations;
+
+use crate::{
+    analysis::{measure_centrality, Centrality},
+    console::{AddConsoleCommand, Arg, CommandEvent, CommandSpec, ConsoleLog},
+    data::MetaRelation,
+    menu::{spawn_menu_panel, PanelAnchor},
+    pipeline::UniverseLoaded,
+    scene::{planet_color, planet_scale, CartographyRes, PlanetComp, PlanetRadius, Selection},
+    universe::build_universe,
+};
+
+const FONT_SIZE: f32 = 16.;
+
+// Centrality planets are sized, shaded and labelled by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Emphasis {
+    Uniform,
+    PageRank,
+    Betweenness,
+    Degree,
+}
+
+// Written out, as #[default] on a variant needs a newer compiler than Bevy 0.6 builds with
+#[allow(clippy::derivable_impls)]
+impl Default for Emphasis {
+    fn default() -> Self {
+        Emphasis::Uniform
+    }
+}
+
+impl Emphasis {
+    const ALL: [Emphasis; 4] = [
+        Emphasis::Uniform,
+        Emphasis::PageRank,
+        Emphasis::Betweenness,
+        Emphasis::Degree,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Emphasis::Uniform => "uniform",
+            Emphasis::PageRank => "pagerank",
+            Emphasis::Betweenness => "betweenness",
+            Emphasis::Degree => "degree",
+        }
+    }
+
+    fn score(&self, centrality: &Centrality) -> Option<f64> {
+        match self {
+            Emphasis::Uniform => None,
+            Emphasis::PageRank => Some(centrality.pagerank),
+            Emphasis::Betweenness => Some(centrality.betweenness),
+            Emphasis::Degree => Some(centrality.weighted_degree),
+        }
+    }
+}
+
+// Centrality and constellation of each planet, as measured on the task pool
+type Measured = Vec<(String, Option<Centrality>, Option<String>)>;
+
+// Centrality and constellations take a while to work out, so they are only measured
+//  once something needs them, then again for every universe loaded after that
+#[derive(Default)]
+pub struct CentralityMeasure {
+    wanted: bool,
+    // Set once the current universe is measured
+    pub measured: bool,
+    receiver: Option<Mutex<Receiver<Measured>>>,
+}
+
+impl CentralityMeasure {
+    pub fn request(&mut self) {
+        self.wanted = true;
+    }
+}
+
+#[derive(Component, Clone)]
+struct InfoPanel;
+
+pub struct CentralityPlugin;
+
+impl Plugin for CentralityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Emphasis>()
+            .init_resource::<CentralityMeasure>()
+            .add_console_command(
+                CommandSpec::new("emphasis")
+                    .usage(&[], "Show what planets are sized and shaded by")
+                    .usage(
+                        &[Arg::Text("uniform|pagerank|betweenness|degree")],
+                        "Size, shade and label planets by how central they are",
+                    ),
+            )
+            .add_startup_system(setup_info_panel)
+            .add_system(start_measuring)
+            .add_system(receive_measurements)
+            .add_system(emphasis_command)
+            .add_system(apply_emphasis)
+            .add_system(update_info_panel);
+    }
+}
+
+fn setup_info_panel(mut commands: Commands, asset_server: Res<AssetServer>) {
+    spawn_menu_panel(
+        &mut commands,
+        &asset_server,
+        PanelAnchor::TopRight,
+        FONT_SIZE,
+        InfoPanel,
+    );
+}
+
+// Measure the universe on the task pool once it is wanted, its tags are copied out
+//  so it can be replaced meanwhile
+fn start_measuring(
+    mut loaded_evr: EventReader<UniverseLoaded>,
+    pool: Res<AsyncComputeTaskPool>,
+    cartography: Res<CartographyRes>,
+    mut measure: ResMut<CentralityMeasure>,
+) {
+    if loaded_evr.iter().count() > 0 {
+        // Results of an older universe still being measured are dropped with the old receiver
+        measure.measured = false;
+        measure.receiver = None;
+    }
+    if !measure.wanted || measure.measured || measure.receiver.is_some() {
+        return;
+    }
+    let relations: Vec<MetaRelation> = cartography
+        .galaxies
+        .values()
+        .map(|galaxy| galaxy.relation.clone())
+        .collect();
+    let (sender, receiver) = channel();
+    measure.receiver = Some(Mutex::new(receiver));
+    pool.spawn(async move {
+        let (galaxies, mut planets) = build_universe(relations);
+        measure_centrality(&galaxies, &mut planets);
+        find_constellations(&galaxies, &mut planets);
+        let measured = planets
+            .into_values()
+            .map(|planet| (planet.name, planet.centrality, planet.constellation))
+            .collect();
+        let _ = sender.send(measured);
+    })
+    .detach();
+}
+
+fn receive_measurements(
+    mut measure: ResMut<CentralityMeasure>,
+    mut cartography: ResMut<CartographyRes>,
+) {
+    let measured = match &measure.receiver {
+        Some(receiver) => match receiver.lock().unwrap().try_recv() {
+            Ok(measured) => measured,
+            Err(_) => return,
+        },
+        None => return,
+    };
+    measure.receiver = None;
+    measure.measured = true;
+    for (name, centrality, constellation) in measured {
+        if let Some(planet) = cartography.planets.get_mut(&name) {
+            planet.centrality = centrality;
+            planet.constellation = constellation;
+        }
+    }
+}
+
+// emphasis [uniform|pagerank|betweenness|degree]
+fn emphasis_command(
+    mut events: EventReader<CommandEvent>,
+    mut emphasis: ResMut<Emphasis>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    for ev in events.iter().filter(|ev| ev.name == "emphasis") {
+        if let Some(name) = ev.args.first() {
+            match Emphasis::ALL.iter().find(|e| e.name() == name.as_str()) {
+                Some(chosen) => *emphasis = *chosen,
+                None => {
+                    log.error(format!("[EMPHASIS] Unknown centrality: {}", name));
+                    continue;
+                }
+            }
+        }
+        log.info(format!("[EMPHASIS] Planets sized by {}", emphasis.name()));
+    }
+}
+
+// Rescale and reshade every planet when the emphasis or the universe changes,
+//  or new planets are spawned
+#[allow(clippy::too_many_arguments)]
+fn apply_emphasis(
+    emphasis: Res<Emphasis>,
+    cartography: Res<CartographyRes>,
+    selection: Res<Selection>,
+    planet_radius: Res<PlanetRadius>,
+    mut measure: ResMut<CentralityMeasure>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    added_q: Query<(), Added<PlanetComp>>,
+    mut planet_q: Query<(&mut PlanetComp, &mut Transform, &Handle<StandardMaterial>)>,
+) {
+    if !emphasis.is_changed() && !cartography.is_changed() && added_q.is_empty() {
+        return;
+    }
+    // Planets keep their size until the universe is measured
+    if *emphasis != Emphasis::Uniform {
+        measure.request();
+    }
+    let score = |name: &String| {
+        cartography
+            .planets
+            .get(name)
+            .and_then(|planet| planet.centrality.as_ref())
+            .and_then(|centrality| emphasis.score(centrality))
+    };
+    let max = planet_q
+        .iter()
+        .filter_map(|(planet, _, _)| score(&planet.name))
+        .fold(0., f64::max);
+
+    for (mut planet, mut planet_tf, material) in planet_q.iter_mut() {
+        // Square root so the few very central planets don't dwarf everything else
+        planet.emphasis = score(&planet.name).map(|score| {
+            if max > 0. {
+                (score / max).sqrt() as f32
+            } else {
+                0.
+            }
+        });
+        planet_tf.scale = planet_scale(planet_radius.0, planet.emphasis);
+        let selected = Some(&planet.name) == selection.planet.as_ref();
+        if let Some(material) = materials.get_mut(material) {
+            material.base_color = planet_color(selected, &planet);
+        }
+    }
+}
+
+// Where a planet ranks among all planets, 1 being the most central
+fn rank(cartography: &CartographyRes, score: impl Fn(&Centrality) -> f64, value: f64) -> usize {
+    1 + cartography
+        .planets
+        .values()
+        .filter(|planet| score(&planet.centrality.unwrap_or_default()) > value)
+        .count()
+}
+
+fn update_info_panel(
+    selection: Res<Selection>,
+    cartography: Res<CartographyRes>,
+    mut measure: ResMut<CentralityMeasure>,
+    mut panel_q: Query<&mut Visibility, With<InfoPanel>>,
+    mut text_q: Query<&mut Text, With<InfoPanel>>,
+) {
+    if !selection.is_changed() && !cartography.is_changed() {
+        return;
+    }
+    let planet = selection
+        .planet
+        .as_ref()
+        .and_then(|name| cartography.planets.get(name));
+    for mut visibility in panel_q.iter_mut() {
+        visibility.is_visible = planet.is_some();
+    }
+    let planet = match planet {
+        Some(planet) => planet,
+        None => return,
+    };
+    measure.request();
+
+    let mut galaxies: Vec<&String> = planet.belong_galaxy.iter().collect();
+    galaxies.sort();
+    let mut lines = vec![
+        planet.name.clone(),
+        format!(
+            "galaxies: {}",
+            galaxies
+                .iter()
+                .map(|g| g.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    ];
+    match planet.centrality {
+        Some(centrality) => {
+            let total = cartography.planets.len();
+            lines.extend([
+                format!(
+                    "pagerank:        {:.5}  #{} of {}",
+                    centrality.pagerank,
+                    rank(&cartography, |c| c.pagerank, centrality.pagerank),
+                    total
+                ),
+                format!(
+                    "betweenness:     {:.5}  #{} of {}",
+                    centrality.betweenness,
+                    rank(&cartography, |c| c.betweenness, centrality.betweenness),
+                    total
+                ),
+                format!(
+                    "weighted degree: {:<7}  #{} of {}",
+                    centrality.weighted_degree,
+                    rank(
+                        &cartography,
+                        |c| c.weighted_degree,
+                        centrality.weighted_degree
+                    ),
+                    total
+                ),
+            ]);
+        }
+        None => lines.push("measuring centrality...".to_string()),
+    }
+    text_q.single_mut().sections[0].value = lines.join("\n");
+}
@@ -0,0 +1,101 @@
+// command line options, described in cli.yml
+use bevy::window::WindowMode;
+use clap::{load_yaml, value_t};
+
+use crate::{
+    console::StartupScripts,
+    data::DatasetSource,
+    headless::{Command, OutputFormat},
+    layout::{LayoutAlgorithm, LayoutOptions},
+    picker::BrowseDirs,
+};
+
+pub struct Options {
+    pub datasets: DatasetSource,
+    pub browse: BrowseDirs,
+    pub width: f32,
+    pub height: f32,
+    pub window_mode: WindowMode,
+    pub layout: LayoutOptions,
+    pub scripts: StartupScripts,
+    // Analysis to run instead of opening the window
+    pub command: Option<Command>,
+    pub format: OutputFormat,
+}
+
+// Parse the command line, printing usage and exiting if it is invalid
+pub fn parse() -> Options {
+    let yaml = load_yaml!("cli.yml");
+    let matches = clap::App::from_yaml(yaml).get_matches();
+
+    let datasets = match matches.values_of("file") {
+        Some(files) => DatasetSource::Files(files.map(|f| f.to_string()).collect()),
+        None => DatasetSource::Dir(matches.value_of("datasets").unwrap().to_string()),
+    };
+    let window_mode = if matches.is_present("fullscreen") {
+        WindowMode::BorderlessFullscreen
+    } else {
+        WindowMode::Windowed
+    };
+    let seed = matches
+        .is_present("seed")
+        .then(|| value_t!(matches, "seed", u64).unwrap_or_else(|e| e.exit()));
+    let algorithm = match matches.value_of("layout") {
+        Some(algorithm) => algorithm.parse().unwrap(),
+        None => LayoutAlgorithm::Random,
+    };
+
+    let command = match matches.subcommand() {
+        ("stats", Some(_)) => Some(Command::Stats),
+        ("top", Some(args)) => Some(Command::Top {
+            domain: args.value_of("domain").unwrap().to_string(),
+            tag: args.value_of("tag").unwrap().to_string(),
+            n: value_t!(args, "n", usize).unwrap_or_else(|e| e.exit()),
+        }),
+        ("path", Some(args)) => Some(Command::Path {
+            domain: args.value_of("domain").unwrap().to_string(),
+            from: args.value_of("from").unwrap().to_string(),
+            to: args.value_of("to").unwrap().to_string(),
+        }),
+        ("export", Some(args)) => Some(Command::Export {
+            output: args.value_of("output").map(|o| o.to_string()),
+        }),
+        _ => None,
+    };
+    // Global, so it can be given before or after the subcommand
+    let format_given = matches
+        .subcommand()
+        .1
+        .and_then(|args| args.value_of("format"))
+        .or_else(|| matches.value_of("format"));
+    let format = match format_given {
+        Some("json") => OutputFormat::Json,
+        _ => OutputFormat::Text,
+    };
+
+    Options {
+        datasets,
+        browse: BrowseDirs(
+            matches
+                .values_of("browse")
+                .map(|dirs| dirs.map(|d| d.to_string()).collect())
+                .unwrap_or_default(),
+        ),
+        width: value_t!(matches, "width", f32).unwrap_or_else(|e| e.exit()),
+        height: value_t!(matches, "height", f32).unwrap_or_else(|e| e.exit()),
+        window_mode,
+        layout: LayoutOptions {
+            algorithm,
+            seed,
+            regenerate: matches.is_present("layout") || seed.is_some(),
+        },
+        scripts: StartupScripts {
+            paths: matches
+                .values_of("exec")
+                .map(|paths| paths.map(|p| p.to_string()).collect())
+                .unwrap_or_default(),
+        },
+        command,
+        format,
+    }
+}
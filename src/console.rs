@@ -0,0 +1,935 @@
+// console command registry, parsing, dispatch, output log and line editing
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fs,
+    path::Path,
+};
+
+use bevy::prelude::*;
+
+use crate::{layout::CACHE_DIR, loading::AppState};
+
+const HISTORY_FILE: &str = "history.txt";
+const MAX_HISTORY: usize = 200;
+// Run on every startup if it exists
+const AUTOEXEC_PATH: &str = "autoexec.cfg";
+// Scripts may exec scripts this deep, so one that execs itself doesn't run forever
+const MAX_EXEC_DEPTH: usize = 8;
+
+// A single argument in a command signature
+#[derive(Debug, Clone, Copy)]
+pub enum Arg {
+    // A fixed word, e.g. the `save` in `bookmark save <name>`
+    Literal(&'static str),
+    Text(&'static str),
+    Number(&'static str),
+    // Swallows all remaining words, which may be none
+    Rest(&'static str),
+}
+
+impl Arg {
+    fn usage(&self) -> String {
+        match self {
+            Arg::Literal(word) => word.to_string(),
+            Arg::Text(name) => format!("<{}>", name),
+            Arg::Number(name) => format!("<{}:number>", name),
+            Arg::Rest(name) => format!("[{}...]", name),
+        }
+    }
+}
+
+// One accepted form of a command, with its own help text
+#[derive(Debug, Clone)]
+pub struct Signature {
+    pub args: Vec<Arg>,
+    pub help: &'static str,
+}
+
+impl Signature {
+    // Check the arguments fit this signature, normalising the case of literals
+    fn matches(&self, args: &[String]) -> Option<Vec<String>> {
+        let mut matched = vec![];
+        for (i, arg) in self.args.iter().enumerate() {
+            match arg {
+                Arg::Rest(_) => {
+                    matched.extend(args[i.min(args.len())..].iter().cloned());
+                    return Some(matched);
+                }
+                Arg::Literal(word) => match args.get(i) {
+                    Some(given) if given.eq_ignore_ascii_case(word) => {
+                        matched.push(word.to_string())
+                    }
+                    _ => return None,
+                },
+                Arg::Text(_) => matched.push(args.get(i)?.clone()),
+                Arg::Number(_) => {
+                    let given = args.get(i)?;
+                    given.parse::<f64>().ok()?;
+                    matched.push(given.clone());
+                }
+            }
+        }
+        if args.len() == self.args.len() {
+            Some(matched)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub signatures: Vec<Signature>,
+}
+
+impl CommandSpec {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            signatures: vec![],
+        }
+    }
+
+    pub fn usage(mut self, args: &[Arg], help: &'static str) -> Self {
+        self.signatures.push(Signature {
+            args: args.to_vec(),
+            help,
+        });
+        self
+    }
+
+    fn usage_lines(&self) -> Vec<String> {
+        self.signatures
+            .iter()
+            .map(|signature| {
+                let words: Vec<String> = std::iter::once(self.name.to_string())
+                    .chain(signature.args.iter().map(|arg| arg.usage()))
+                    .collect();
+                format!("{} - {}", words.join(" "), signature.help)
+            })
+            .collect()
+    }
+}
+
+// All commands known to the console, registered by each plugin
+#[derive(Default)]
+pub struct CommandRegistry {
+    commands: BTreeMap<&'static str, CommandSpec>,
+}
+
+impl CommandRegistry {
+    pub fn names(&self) -> Vec<String> {
+        self.commands.keys().map(|name| name.to_string()).collect()
+    }
+
+    // Turn a line of input into a validated command ready to dispatch
+    pub fn parse(&self, line: &str) -> Result<Option<CommandEvent>, String> {
+        let mut words = tokenize(line)?.into_iter();
+        let name = match words.next() {
+            Some(name) => name.to_lowercase(),
+            None => return Ok(None),
+        };
+        let args: Vec<String> = words.collect();
+
+        let spec = self
+            .commands
+            .get(name.as_str())
+            .ok_or_else(|| format!("Unknown command '{}', type 'help' for a list", name))?;
+        match spec.signatures.iter().find_map(|s| s.matches(&args)) {
+            Some(args) => Ok(Some(CommandEvent {
+                name,
+                args,
+                depth: 0,
+            })),
+            None => Err(format!("Usage:\n  {}", spec.usage_lines().join("\n  "))),
+        }
+    }
+}
+
+// Split a line into words, keeping quoted text together, e.g. `tour record "Hello world"`
+pub fn tokenize(line: &str) -> Result<Vec<String>, String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), '\\') => match chars.next() {
+                Some(escaped) => word.push(escaped),
+                None => return Err("Unterminated escape".to_string()),
+            },
+            (Some(_), c) => word.push(c),
+            (None, '"') | (None, '\'') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return Err("Unterminated quote".to_string());
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+// A validated console command, picked up by whichever plugin registered it
+pub struct CommandEvent {
+    pub name: String,
+    pub args: Vec<String>,
+    // How many scripts deep it was run from, 0 when typed
+    pub depth: usize,
+}
+
+// Lines waiting to be run, one per frame so state changes from each can settle
+#[derive(Default)]
+pub struct PendingCommands {
+    // Each line with how many scripts deep it was queued from
+    lines: Vec<(String, usize)>,
+}
+
+impl PendingCommands {
+    // Queue a typed line after everything already waiting
+    pub fn push(&mut self, line: String) {
+        self.lines.push((line, 0));
+    }
+
+    // Queue a script ahead of everything already waiting, for a command run depth scripts deep
+    fn exec(&mut self, path: &str, depth: usize) -> Result<usize, String> {
+        if depth >= MAX_EXEC_DEPTH {
+            return Err(format!(
+                "{}: scripts are nested more than {} deep, does one run itself?",
+                path, MAX_EXEC_DEPTH
+            ));
+        }
+        let lines = read_script(path)?;
+        let count = lines.len();
+        self.lines
+            .splice(0..0, lines.into_iter().map(|line| (line, depth + 1)));
+        Ok(count)
+    }
+}
+
+// Oldest log lines are dropped once the log grows past this
+const MAX_LOG_LINES: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    // The command line itself, as typed
+    Echo,
+    Info,
+    Warning,
+    Error,
+}
+
+// Everything commands have printed, shown in the console scrollback
+#[derive(Default)]
+pub struct ConsoleLog {
+    lines: VecDeque<(Severity, String)>,
+}
+
+impl ConsoleLog {
+    pub fn lines(&self) -> &VecDeque<(Severity, String)> {
+        &self.lines
+    }
+
+    // Add a (possibly multi-line) message, also printing it to stdout
+    pub fn push(&mut self, severity: Severity, message: impl Into<String>) {
+        for line in message.into().lines() {
+            println!("{}", line);
+            self.lines.push_back((severity, line.to_string()));
+        }
+        while self.lines.len() > MAX_LOG_LINES {
+            self.lines.pop_front();
+        }
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.push(Severity::Info, message);
+    }
+
+    pub fn warn(&mut self, message: impl Into<String>) {
+        self.push(Severity::Warning, message);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.push(Severity::Error, message);
+    }
+}
+
+// Text being typed into the console, with a caret that can be moved around.
+//  The caret is a byte offset that always sits on a char boundary
+#[derive(Default)]
+pub struct LineEditor {
+    text: String,
+    caret: usize,
+}
+
+impl LineEditor {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn caret(&self) -> usize {
+        self.caret
+    }
+
+    // Replace the whole line, leaving the caret at the end
+    pub fn set(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.caret = self.text.len();
+    }
+
+    pub fn take(&mut self) -> String {
+        self.caret = 0;
+        std::mem::take(&mut self.text)
+    }
+
+    pub fn insert(&mut self, text: &str) {
+        self.text.insert_str(self.caret, text);
+        self.caret += text.len();
+    }
+
+    pub fn backspace(&mut self) {
+        let start = self.prev_boundary();
+        self.text.replace_range(start..self.caret, "");
+        self.caret = start;
+    }
+
+    pub fn delete(&mut self) {
+        let end = self.next_boundary();
+        self.text.replace_range(self.caret..end, "");
+    }
+
+    // Delete back to the start of the previous word, like ctrl+w in a shell
+    pub fn delete_word(&mut self) {
+        let start = self.word_start();
+        self.text.replace_range(start..self.caret, "");
+        self.caret = start;
+    }
+
+    pub fn left(&mut self) {
+        self.caret = self.prev_boundary();
+    }
+
+    pub fn right(&mut self) {
+        self.caret = self.next_boundary();
+    }
+
+    pub fn word_left(&mut self) {
+        self.caret = self.word_start();
+    }
+
+    pub fn word_right(&mut self) {
+        let rest = &self.text[self.caret..];
+        let skip_space = rest.len() - rest.trim_start().len();
+        let word = rest[skip_space..]
+            .find(char::is_whitespace)
+            .unwrap_or(rest.len() - skip_space);
+        self.caret += skip_space + word;
+    }
+
+    pub fn home(&mut self) {
+        self.caret = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.caret = self.text.len();
+    }
+
+    // Complete the word before the caret from command names (first word) or other words,
+    //  returning every candidate that matched
+    pub fn complete(&mut self, commands: &[String], words: &[String]) -> Vec<String> {
+        let start = self.text[..self.caret]
+            .rfind(char::is_whitespace)
+            .map_or(0, |i| i + 1);
+        let prefix = &self.text[start..self.caret];
+        let candidates = if self.text[..start].trim().is_empty() {
+            commands
+        } else {
+            words
+        };
+
+        let mut matches: Vec<String> = candidates
+            .iter()
+            .filter(|c| c.starts_with(prefix))
+            .cloned()
+            .collect();
+        matches.sort();
+        matches.dedup();
+
+        let completion = match matches.as_slice() {
+            [] => return matches,
+            [only] => format!("{} ", only),
+            [first, rest @ ..] => {
+                // Extend as far as all candidates agree
+                let mut common = first.clone();
+                for m in rest {
+                    while !m.starts_with(common.as_str()) {
+                        common.pop();
+                    }
+                }
+                common
+            }
+        };
+        self.text.replace_range(start..self.caret, &completion);
+        self.caret = start + completion.len();
+        matches
+    }
+
+    fn prev_boundary(&self) -> usize {
+        self.text[..self.caret]
+            .chars()
+            .next_back()
+            .map_or(0, |c| self.caret - c.len_utf8())
+    }
+
+    fn next_boundary(&self) -> usize {
+        self.text[self.caret..]
+            .chars()
+            .next()
+            .map_or(self.caret, |c| self.caret + c.len_utf8())
+    }
+
+    fn word_start(&self) -> usize {
+        let before = self.text[..self.caret].trim_end();
+        before.rfind(char::is_whitespace).map_or(0, |i| i + 1)
+    }
+}
+
+// Previously entered lines, persisted between sessions
+#[derive(Default)]
+pub struct CommandHistory {
+    entries: Vec<String>,
+    // Entry currently recalled with up/down, if any
+    browsing: Option<usize>,
+    // What was being typed before browsing started
+    draft: String,
+    // File the entries are saved to
+    path: String,
+}
+
+impl CommandHistory {
+    pub fn load() -> CommandHistory {
+        CommandHistory::load_from(&format!("{}{}", CACHE_DIR, HISTORY_FILE))
+    }
+
+    fn load_from(path: &str) -> CommandHistory {
+        let entries = fs::read_to_string(path)
+            .map(|content| content.lines().map(|l| l.to_string()).collect())
+            .unwrap_or_default();
+        CommandHistory {
+            entries,
+            path: path.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn push(&mut self, line: &str) -> Result<(), String> {
+        self.browsing = None;
+        if line.trim().is_empty() || self.entries.last().map(|l| l.as_str()) == Some(line) {
+            return Ok(());
+        }
+        self.entries.push(line.to_string());
+        if self.entries.len() > MAX_HISTORY {
+            self.entries.remove(0);
+        }
+
+        if let Some(dir) = Path::new(&self.path).parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        fs::write(&self.path, self.entries.join("\n")).map_err(|e| e.to_string())
+    }
+
+    // Step back through history, remembering the current line to come back to
+    pub fn previous(&mut self, current: &str) -> Option<&str> {
+        let index = match self.browsing {
+            Some(0) => 0,
+            Some(i) => i - 1,
+            None if self.entries.is_empty() => return None,
+            None => {
+                self.draft = current.to_string();
+                self.entries.len() - 1
+            }
+        };
+        self.browsing = Some(index);
+        Some(&self.entries[index])
+    }
+
+    pub fn next(&mut self) -> Option<&str> {
+        let index = self.browsing? + 1;
+        if index >= self.entries.len() {
+            self.browsing = None;
+            return Some(&self.draft);
+        }
+        self.browsing = Some(index);
+        Some(&self.entries[index])
+    }
+}
+
+// Script files given on the command line, run once everything has started after autoexec.cfg
+#[derive(Default)]
+pub struct StartupScripts {
+    pub paths: Vec<String>,
+}
+
+// Read a script of console commands, one per line.
+//  Blank lines and lines starting with `#` are skipped
+pub fn read_script(path: &str) -> Result<Vec<String>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    Ok(content
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.to_string())
+        .collect())
+}
+
+pub trait AddConsoleCommand {
+    fn add_console_command(&mut self, spec: CommandSpec) -> &mut Self;
+}
+
+impl AddConsoleCommand for App {
+    fn add_console_command(&mut self, spec: CommandSpec) -> &mut Self {
+        self.world
+            .get_resource_or_insert_with(CommandRegistry::default)
+            .commands
+            .insert(spec.name, spec);
+        self
+    }
+}
+
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CommandRegistry>()
+            .insert_resource(PendingCommands::default())
+            .insert_resource(ConsoleLog::default())
+            .insert_resource(LineEditor::default())
+            .insert_resource(CommandHistory::load())
+            .init_resource::<StartupScripts>()
+            .add_event::<CommandEvent>()
+            .add_console_command(
+                CommandSpec::new("help")
+                    .usage(&[], "List all commands")
+                    .usage(&[Arg::Text("command")], "Show how to use a command"),
+            )
+            .add_console_command(
+                CommandSpec::new("exec").usage(&[Arg::Text("file")], "Run commands from a script"),
+            )
+            // Scripts expect the universe to be there
+            .add_system_set(SystemSet::on_enter(AppState::Running).with_system(run_startup_scripts))
+            .add_system(process_commands)
+            .add_system(help_command)
+            .add_system(exec_command);
+    }
+}
+
+fn process_commands(
+    registry: Res<CommandRegistry>,
+    mut pending: ResMut<PendingCommands>,
+    mut log: ResMut<ConsoleLog>,
+    mut command_events: EventWriter<CommandEvent>,
+) {
+    if pending.lines.is_empty() {
+        return;
+    }
+    let (line, depth) = pending.lines.remove(0);
+    if line.trim().is_empty() {
+        return;
+    }
+    log.push(Severity::Echo, format!("> {}", line));
+    match registry.parse(&line) {
+        Ok(Some(command)) => command_events.send(CommandEvent { depth, ..command }),
+        Ok(None) => {}
+        Err(e) => log.error(e),
+    }
+}
+
+fn help_command(
+    registry: Res<CommandRegistry>,
+    mut events: EventReader<CommandEvent>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    for ev in events.iter().filter(|ev| ev.name == "help") {
+        match ev.args.first() {
+            Some(name) => match registry.commands.get(name.to_lowercase().as_str()) {
+                Some(spec) => log.info(spec.usage_lines().join("\n")),
+                None => log.error(format!("Unknown command '{}'", name)),
+            },
+            None => {
+                for spec in registry.commands.values() {
+                    log.info(spec.usage_lines().join("\n"));
+                }
+            }
+        }
+    }
+}
+
+fn run_startup_scripts(
+    scripts: Res<StartupScripts>,
+    mut pending: ResMut<PendingCommands>,
+    mut log: ResMut<ConsoleLog>,
+    mut ran: Local<bool>,
+) {
+    // Running is entered again whenever the datasets are reloaded
+    if *ran {
+        return;
+    }
+    *ran = true;
+    let autoexec = Path::new(AUTOEXEC_PATH)
+        .exists()
+        .then(|| AUTOEXEC_PATH.to_string());
+    for path in autoexec.iter().chain(scripts.paths.iter()) {
+        match read_script(path) {
+            Ok(lines) => {
+                log.info(format!("[EXEC] {} ({} commands)", path, lines.len()));
+                pending
+                    .lines
+                    .extend(lines.into_iter().map(|line| (line, 1)));
+            }
+            Err(e) => log.error(format!("[EXEC] {}", e)),
+        }
+    }
+}
+
+// exec <file>
+fn exec_command(
+    mut events: EventReader<CommandEvent>,
+    mut pending: ResMut<PendingCommands>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    for ev in events.iter().filter(|ev| ev.name == "exec") {
+        // Run the script before anything queued after the exec
+        match pending.exec(&ev.args[0], ev.depth) {
+            Ok(count) => log.info(format!("[EXEC] {} ({} commands)", ev.args[0], count)),
+            Err(e) => log.error(format!("[EXEC] {}", e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(line: &str) -> Vec<String> {
+        tokenize(line).unwrap()
+    }
+
+    #[test]
+    fn quoted_text_is_one_word() {
+        assert_eq!(words("  frame   galaxy unix "), ["frame", "galaxy", "unix"]);
+        assert_eq!(
+            words(r#"tour record "Hello world" 'it''s'"#),
+            ["tour", "record", "Hello world", "its"]
+        );
+        // Quotes can start mid word, and an empty pair is still a word
+        assert_eq!(words(r#"a"b c"d """#), ["ab cd", ""]);
+        assert_eq!(words(r#""say \"hi\"" 'a\\b'"#), [r#"say "hi""#, r"a\b"]);
+        // Outside quotes a backslash is just a character
+        assert_eq!(words(r"c:\temp"), [r"c:\temp"]);
+        assert!(words("   ").is_empty());
+    }
+
+    #[test]
+    fn unterminated_quotes_are_rejected() {
+        assert_eq!(tokenize(r#"echo "hi"#).unwrap_err(), "Unterminated quote");
+        assert_eq!(tokenize("echo 'hi").unwrap_err(), "Unterminated quote");
+        assert_eq!(tokenize(r#"echo "hi\"#).unwrap_err(), "Unterminated escape");
+    }
+
+    fn registry() -> CommandRegistry {
+        let mut registry = CommandRegistry::default();
+        for spec in [
+            CommandSpec::new("bookmark")
+                .usage(&[Arg::Literal("list")], "List")
+                .usage(&[Arg::Literal("save"), Arg::Text("name")], "Save"),
+            CommandSpec::new("set").usage(&[Arg::Text("cvar"), Arg::Number("value")], "Set"),
+            CommandSpec::new("echo").usage(&[Arg::Rest("words")], "Echo"),
+        ] {
+            registry.commands.insert(spec.name, spec);
+        }
+        registry
+    }
+
+    fn parse(line: &str) -> Result<(String, Vec<String>), String> {
+        let command = registry().parse(line)?.expect("a command");
+        Ok((command.name, command.args))
+    }
+
+    #[test]
+    fn arguments_are_checked_against_signatures() {
+        // Names and literals are matched ignoring case, and normalised
+        assert_eq!(
+            parse("BOOKMARK Save Home").unwrap(),
+            (
+                "bookmark".to_string(),
+                vec!["save".to_string(), "Home".to_string()]
+            )
+        );
+        assert_eq!(parse("bookmark list").unwrap().1, ["list"]);
+        assert!(parse("bookmark load home").is_err());
+
+        assert_eq!(
+            parse("set planet_radius 2.5").unwrap().1,
+            ["planet_radius", "2.5"]
+        );
+        assert_eq!(
+            parse("set planet_radius -1").unwrap().1,
+            ["planet_radius", "-1"]
+        );
+        assert!(parse("set planet_radius big").is_err());
+
+        assert!(parse("echo").unwrap().1.is_empty());
+        assert_eq!(parse("echo a 'b c' d").unwrap().1, ["a", "b c", "d"]);
+    }
+
+    #[test]
+    fn wrong_numbers_of_arguments_are_rejected() {
+        for line in [
+            "bookmark",
+            "bookmark save",
+            "bookmark save a b",
+            "bookmark list all",
+        ] {
+            let e = parse(line).unwrap_err();
+            assert!(e.starts_with("Usage:\n  bookmark list - List\n  bookmark save <name> - Save"));
+        }
+        assert!(parse("set planet_radius").is_err());
+        assert!(parse("set planet_radius 1 2").is_err());
+    }
+
+    #[test]
+    fn unknown_commands_and_blank_lines() {
+        assert!(parse("warp 9")
+            .unwrap_err()
+            .starts_with("Unknown command 'warp'"));
+        assert!(registry().parse("   ").unwrap().is_none());
+        assert_eq!(
+            registry().parse("echo 'oops").err().as_deref(),
+            Some("Unterminated quote")
+        );
+    }
+
+    #[test]
+    fn log_drops_the_oldest_lines() {
+        let mut log = ConsoleLog::default();
+        log.info("first\nsecond");
+        assert_eq!(log.lines().len(), 2);
+        for i in 0..MAX_LOG_LINES {
+            log.warn(format!("line {}", i));
+        }
+        assert_eq!(log.lines().len(), MAX_LOG_LINES);
+        assert_eq!(log.lines()[0], (Severity::Warning, "line 0".to_string()));
+        log.error("last");
+        assert_eq!(log.lines()[0].1, "line 1");
+        assert_eq!(
+            log.lines()[MAX_LOG_LINES - 1],
+            (Severity::Error, "last".to_string())
+        );
+    }
+
+    #[test]
+    fn editor_moves_over_whole_characters() {
+        let mut editor = LineEditor::default();
+        editor.insert("héllo");
+        assert_eq!(editor.caret(), "héllo".len());
+        editor.left();
+        editor.left();
+        editor.left();
+        editor.left();
+        assert_eq!(editor.caret(), 1);
+        editor.right();
+        assert_eq!(editor.caret(), 3);
+        editor.backspace();
+        assert_eq!((editor.text(), editor.caret()), ("hllo", 1));
+        editor.insert("e");
+        editor.delete();
+        assert_eq!((editor.text(), editor.caret()), ("helo", 2));
+
+        // Nothing to remove or move past at either end
+        editor.home();
+        editor.backspace();
+        editor.left();
+        assert_eq!((editor.text(), editor.caret()), ("helo", 0));
+        editor.end();
+        editor.delete();
+        editor.right();
+        assert_eq!((editor.text(), editor.caret()), ("helo", 4));
+
+        assert_eq!(editor.take(), "helo");
+        assert_eq!((editor.text(), editor.caret()), ("", 0));
+    }
+
+    #[test]
+    fn editor_word_operations() {
+        let mut editor = LineEditor::default();
+        editor.set("frame  galaxy unix  ");
+        editor.word_left();
+        assert_eq!(editor.caret(), "frame  galaxy ".len());
+        editor.word_left();
+        assert_eq!(editor.caret(), "frame  ".len());
+        editor.word_left();
+        editor.word_left();
+        assert_eq!(editor.caret(), 0);
+
+        editor.word_right();
+        assert_eq!(editor.caret(), "frame".len());
+        editor.word_right();
+        assert_eq!(editor.caret(), "frame  galaxy".len());
+        editor.end();
+        editor.word_right();
+        assert_eq!(editor.caret(), editor.text().len());
+
+        // Trailing spaces go with the word before them
+        editor.delete_word();
+        assert_eq!(editor.text(), "frame  galaxy ");
+        editor.word_left();
+        editor.delete_word();
+        assert_eq!((editor.text(), editor.caret()), ("galaxy ", 0));
+    }
+
+    #[test]
+    fn editor_completes_commands_then_words() {
+        let commands = vec![
+            "bookmark".to_string(),
+            "tour".to_string(),
+            "top".to_string(),
+        ];
+        let words = vec!["unix".to_string(), "ubuntu".to_string()];
+        let mut editor = LineEditor::default();
+        editor.set("bo");
+        assert_eq!(editor.complete(&commands, &words), ["bookmark"]);
+        assert_eq!(editor.text(), "bookmark ");
+
+        // Several candidates complete as far as they agree
+        editor.set("frame u");
+        assert_eq!(editor.complete(&commands, &words), ["ubuntu", "unix"]);
+        assert_eq!(editor.text(), "frame u");
+        editor.set("t");
+        assert_eq!(editor.complete(&commands, &words), ["top", "tour"]);
+        assert_eq!(editor.text(), "to");
+        editor.insert("u");
+        assert_eq!(editor.complete(&commands, &words), ["tour"]);
+        assert_eq!(editor.text(), "tour ");
+
+        editor.set("warp");
+        assert!(editor.complete(&commands, &words).is_empty());
+        assert_eq!(editor.text(), "warp");
+    }
+
+    fn history(entries: &[&str]) -> CommandHistory {
+        CommandHistory {
+            entries: entries.iter().map(|e| e.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn history_stops_at_the_oldest_entry() {
+        assert_eq!(history(&[]).previous("typing"), None);
+
+        let mut history = history(&["one", "two", "three"]);
+        assert_eq!(history.next(), None);
+        assert_eq!(history.previous("typing"), Some("three"));
+        assert_eq!(history.previous("three"), Some("two"));
+        assert_eq!(history.previous("two"), Some("one"));
+        assert_eq!(history.previous("one"), Some("one"));
+        assert_eq!(history.next(), Some("two"));
+        assert_eq!(history.next(), Some("three"));
+        // Past the newest entry is what was being typed
+        assert_eq!(history.next(), Some("typing"));
+        assert_eq!(history.next(), None);
+        assert_eq!(history.previous("again"), Some("three"));
+        assert_eq!(history.next(), Some("again"));
+    }
+
+    #[test]
+    fn history_is_saved_without_repeats() {
+        let dir = std::env::temp_dir().join(format!("sok-history-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("history.txt").to_string_lossy().to_string();
+
+        let mut history = CommandHistory::load_from(&path);
+        assert_eq!(history.previous(""), None);
+        for line in ["stats", "stats", "  ", "frame", "stats"] {
+            history.push(line).unwrap();
+        }
+        assert_eq!(history.entries, ["stats", "frame", "stats"]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "stats\nframe\nstats");
+
+        let mut loaded = CommandHistory::load_from(&path);
+        assert_eq!(loaded.entries, history.entries);
+        for i in 0..MAX_HISTORY {
+            loaded.push(&format!("line {}", i)).unwrap();
+        }
+        assert_eq!(loaded.entries.len(), MAX_HISTORY);
+        assert_eq!(loaded.entries[0], "line 0");
+        // Pushing stops browsing
+        loaded.previous("");
+        loaded.push("last").unwrap();
+        assert_eq!(loaded.next(), None);
+        assert_eq!(CommandHistory::load_from(&path).entries[0], "line 1");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn scripts_that_exec_themselves_stop() {
+        let path = std::env::temp_dir()
+            .join(format!("sok-loop-{}.cfg", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        fs::write(&path, format!("# runs itself\nexec '{}'\nstats\n", path)).unwrap();
+        let registry = CommandRegistry {
+            commands: [
+                CommandSpec::new("exec").usage(&[Arg::Text("file")], "Exec"),
+                CommandSpec::new("stats").usage(&[], "Stats"),
+            ]
+            .into_iter()
+            .map(|spec| (spec.name, spec))
+            .collect(),
+        };
+
+        let mut pending = PendingCommands::default();
+        pending.push(format!("exec '{}'", path));
+        let mut ran = vec![];
+        let mut errors = vec![];
+        while !pending.lines.is_empty() {
+            let (line, depth) = pending.lines.remove(0);
+            let command = registry.parse(&line).unwrap().unwrap();
+            if command.name == "exec" {
+                if let Err(e) = pending.exec(&command.args[0], depth) {
+                    errors.push(e);
+                }
+            }
+            ran.push((command.name, depth));
+        }
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("nested more than 8 deep"));
+        // Each level runs its stats once the levels below it are done
+        let stats: Vec<usize> = ran
+            .iter()
+            .filter(|(name, _)| name == "stats")
+            .map(|(_, depth)| *depth)
+            .collect();
+        assert_eq!(stats, (1..=MAX_EXEC_DEPTH).rev().collect::<Vec<_>>());
+    }
+}
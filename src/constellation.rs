@@ -0,0 +1,282 @@
+// a menu of the constellations found in the universe, to jump to them and color planets by them
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    camera::FrameRequest,
+    centrality::CentralityMeasure,
+    console::{AddConsoleCommand, Arg, CommandEvent, CommandSpec, ConsoleLog},
+    controls::{Action, Actions, OpenMenu},
+    menu::{menu_sections, spawn_menu_panel, PanelAnchor},
+    scene::{planet_color, CartographyRes, PlanetComp, Selection},
+};
+
+const FONT_SIZE: f32 = 16.;
+const HELP: &str = "Up/Down: move    Enter: jump    C: color planets    F6: close\n\n";
+// Constellations listed in the menu at once, around the highlighted one
+const MENU_ROWS: usize = 20;
+// Degrees between the hues of successive constellations, so neighbours in the list differ
+const HUE_STEP: f32 = 137.5;
+
+#[derive(Default)]
+pub struct Constellations {
+    // Name and planet count of each constellation, largest first
+    pub list: Vec<(String, usize)>,
+    // Color of each constellation, from its place in the list
+    colors: HashMap<String, Color>,
+    // Constellation highlighted in the menu
+    cursor: usize,
+    // Set while planets are colored by constellation
+    pub colored: bool,
+}
+
+pub enum ConstellationRequest {
+    ToggleMenu,
+    // Move the highlight up or down by a number of constellations
+    Move(i32),
+    // Frame the highlighted constellation
+    JumpCursor,
+    List,
+    // Color planets by constellation, or flip it without a value
+    Color(Option<bool>),
+}
+
+#[derive(Component, Clone)]
+struct ConstellationPanel;
+
+pub struct ConstellationPlugin;
+
+impl Plugin for ConstellationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Constellations>()
+            .add_event::<ConstellationRequest>()
+            .add_console_command(
+                CommandSpec::new("constellation")
+                    .usage(&[], "List constellations, largest first")
+                    .usage(
+                        &[Arg::Literal("color"), Arg::Text("on|off")],
+                        "Color planets by the constellation they are in",
+                    ),
+            )
+            .add_startup_system(setup_constellation_panel)
+            .add_system(list_constellations)
+            .add_system(constellation_control)
+            .add_system(constellation_command)
+            .add_system(handle_constellations)
+            .add_system(color_constellations)
+            .add_system(update_constellation_panel);
+    }
+}
+
+fn setup_constellation_panel(mut commands: Commands, asset_server: Res<AssetServer>) {
+    spawn_menu_panel(
+        &mut commands,
+        &asset_server,
+        PanelAnchor::TopLeft,
+        FONT_SIZE,
+        ConstellationPanel,
+    );
+}
+
+// Group planets by constellation whenever the universe is rebuilt
+fn list_constellations(
+    cartography: Res<CartographyRes>,
+    mut constellations: ResMut<Constellations>,
+) {
+    if !cartography.is_changed() {
+        return;
+    }
+    let mut sizes: HashMap<&String, usize> = HashMap::new();
+    for constellation in cartography
+        .planets
+        .values()
+        .filter_map(|planet| planet.constellation.as_ref())
+    {
+        *sizes.entry(constellation).or_default() += 1;
+    }
+    let mut list: Vec<(String, usize)> = sizes
+        .into_iter()
+        .map(|(name, size)| (name.clone(), size))
+        .collect();
+    list.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    // Each constellation keeps its color as long as it keeps its place in the list
+    constellations.colors = list
+        .iter()
+        .enumerate()
+        .map(|(rank, (name, _))| {
+            let hue = (rank as f32 * HUE_STEP) % 360.;
+            (name.clone(), Color::hsl(hue, 0.75, 0.6))
+        })
+        .collect();
+    constellations.cursor = constellations.cursor.min(list.len().saturating_sub(1));
+    constellations.list = list;
+}
+
+fn constellation_control(
+    actions: Res<Actions>,
+    menu: Res<OpenMenu>,
+    mut requests: EventWriter<ConstellationRequest>,
+) {
+    if actions.just_pressed(Action::ToggleConstellations) {
+        requests.send(ConstellationRequest::ToggleMenu);
+    }
+    if *menu != OpenMenu::Constellations {
+        return;
+    }
+    if actions.just_pressed(Action::MenuUp) {
+        requests.send(ConstellationRequest::Move(-1));
+    }
+    if actions.just_pressed(Action::MenuDown) {
+        requests.send(ConstellationRequest::Move(1));
+    }
+    if actions.just_pressed(Action::MenuTick) {
+        requests.send(ConstellationRequest::JumpCursor);
+    }
+    if actions.just_pressed(Action::ColorConstellations) {
+        requests.send(ConstellationRequest::Color(None));
+    }
+}
+
+// constellation
+// constellation color on|off
+fn constellation_command(
+    mut events: EventReader<CommandEvent>,
+    mut requests: EventWriter<ConstellationRequest>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    for ev in events.iter().filter(|ev| ev.name == "constellation") {
+        let args: Vec<&str> = ev.args.iter().map(|a| a.as_str()).collect();
+        match args.as_slice() {
+            [] => requests.send(ConstellationRequest::List),
+            ["color", "on"] => requests.send(ConstellationRequest::Color(Some(true))),
+            ["color", "off"] => requests.send(ConstellationRequest::Color(Some(false))),
+            ["color", other] => {
+                log.error(format!("[CONSTELLATION] Expected on or off, got {}", other))
+            }
+            _ => {}
+        }
+    }
+}
+
+fn handle_constellations(
+    mut requests: EventReader<ConstellationRequest>,
+    mut constellations: ResMut<Constellations>,
+    mut measure: ResMut<CentralityMeasure>,
+    mut menu: ResMut<OpenMenu>,
+    mut frame_evw: EventWriter<FrameRequest>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    for request in requests.iter() {
+        // Constellations are only found once they are asked for
+        if !matches!(request, ConstellationRequest::Move(_)) {
+            measure.request();
+        }
+        match request {
+            ConstellationRequest::ToggleMenu => menu.toggle(OpenMenu::Constellations),
+            ConstellationRequest::Move(by) => {
+                let last = constellations.list.len().saturating_sub(1) as i32;
+                constellations.cursor = (constellations.cursor as i32 + by).clamp(0, last) as usize;
+            }
+            ConstellationRequest::JumpCursor => {
+                if let Some((name, _)) = constellations.list.get(constellations.cursor) {
+                    frame_evw.send(FrameRequest::Constellation(name.clone()));
+                }
+            }
+            ConstellationRequest::List if !measure.measured => {
+                log.warn("[CONSTELLATION] Still finding constellations, try again in a moment");
+            }
+            ConstellationRequest::List => {
+                for (name, size) in constellations.list.iter() {
+                    log.info(format!("[CONSTELLATION] {} ({} planets)", name, size));
+                }
+                log.info("[CONSTELLATION] Jump to one with frame constellation <name>");
+            }
+            ConstellationRequest::Color(colored) => {
+                constellations.colored = colored.unwrap_or(!constellations.colored);
+            }
+        }
+    }
+}
+
+// Recolor every planet when the coloring or the constellations change, or new planets are spawned
+fn color_constellations(
+    constellations: Res<Constellations>,
+    cartography: Res<CartographyRes>,
+    selection: Res<Selection>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    added_q: Query<(), Added<PlanetComp>>,
+    mut planet_q: Query<(&mut PlanetComp, &Handle<StandardMaterial>)>,
+) {
+    if !constellations.is_changed() && added_q.is_empty() {
+        return;
+    }
+    for (mut planet, material) in planet_q.iter_mut() {
+        planet.constellation_color = match cartography.planets.get(&planet.name) {
+            Some(p) if constellations.colored => p
+                .constellation
+                .as_ref()
+                .and_then(|name| constellations.colors.get(name).copied()),
+            _ => None,
+        };
+        let selected = Some(&planet.name) == selection.planet.as_ref();
+        if let Some(material) = materials.get_mut(material) {
+            material.base_color = planet_color(selected, &planet);
+        }
+    }
+}
+
+fn update_constellation_panel(
+    constellations: Res<Constellations>,
+    measure: Res<CentralityMeasure>,
+    menu: Res<OpenMenu>,
+    mut panel_q: Query<&mut Visibility, With<ConstellationPanel>>,
+    mut text_q: Query<&mut Text, With<ConstellationPanel>>,
+) {
+    if !constellations.is_changed() && !measure.is_changed() && !menu.is_changed() {
+        return;
+    }
+    for mut visibility in panel_q.iter_mut() {
+        visibility.is_visible = *menu == OpenMenu::Constellations;
+    }
+
+    let mut text = text_q.single_mut();
+    let style = text.sections[0].style.clone();
+    if !measure.measured {
+        let finding = ("Finding constellations...".to_string(), Color::WHITE);
+        text.sections = menu_sections(&style, HELP, [finding]);
+        return;
+    }
+    let first = constellations
+        .cursor
+        .saturating_sub(MENU_ROWS / 2)
+        .min(constellations.list.len().saturating_sub(MENU_ROWS));
+    let mut rows = vec![];
+    for (index, (name, size)) in constellations
+        .list
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(MENU_ROWS)
+    {
+        let color = match (index == constellations.cursor, constellations.colored) {
+            (true, _) => Color::YELLOW,
+            (false, true) => constellations
+                .colors
+                .get(name)
+                .copied()
+                .unwrap_or(Color::WHITE),
+            (false, false) => Color::WHITE,
+        };
+        rows.push((format!("{:<30} {:>6} planets\n", name, size), color));
+    }
+    rows.push((
+        format!(
+            "\n{} of {} constellations",
+            constellations.cursor + 1,
+            constellations.list.len()
+        ),
+        Color::GRAY,
+    ));
+    text.sections = menu_sections(&style, HELP, rows);
+}
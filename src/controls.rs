@@ -0,0 +1,519 @@
+// named input actions and their rebindable keys/buttons
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+};
+
+use bevy::{input::InputSystem, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    console::{AddConsoleCommand, Arg, CommandEvent, CommandSpec, ConsoleLog},
+    debug::ConsoleState,
+};
+
+const BINDINGS_PATH: &str = "bindings.ron";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    Boost,
+    SpeedUp,
+    SpeedDown,
+    LookLeft,
+    LookRight,
+    LookUp,
+    LookDown,
+    Select,
+    FrameAll,
+    SaveBookmark,
+    RecallBookmark,
+    ToggleConsole,
+    // Skip the dataset files that haven't been loaded yet
+    CancelLoading,
+    // Open the menu of dataset files
+    TogglePicker,
+    // Open the menu of galaxies to show or hide
+    ToggleGalaxies,
+    // Open the graph statistics of the universe
+    ToggleStats,
+    // Open the list of constellations to jump to
+    ToggleConstellations,
+    // Move through the open menu and tick its entries
+    MenuUp,
+    MenuDown,
+    MenuTick,
+    // Rebuild the universe from the ticked dataset files
+    PickerLoad,
+    // Color planets by the constellation they are in
+    ColorConstellations,
+    Exit,
+}
+
+// A single physical input that can trigger an action
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButtonType),
+    // One half of a gamepad stick axis
+    GamepadAxis(GamepadAxisType, AxisDirection),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AxisDirection {
+    Positive,
+    Negative,
+}
+
+impl Binding {
+    // Parse a console argument such as `W`, `f1`, `mouse:Left`, `pad:South` or `axis:LeftStickX+`
+    fn parse(input: &str) -> Option<Binding> {
+        if let Some(axis) = input.strip_prefix("axis:") {
+            let (axis, direction) = match axis.strip_suffix('-') {
+                Some(axis) => (axis, AxisDirection::Negative),
+                None => (axis.trim_end_matches('+'), AxisDirection::Positive),
+            };
+            return parse_variant(axis).map(|axis| Binding::GamepadAxis(axis, direction));
+        }
+        if let Some(button) = input.strip_prefix("mouse:") {
+            return parse_variant(button).map(Binding::Mouse);
+        }
+        if let Some(button) = input.strip_prefix("pad:") {
+            return parse_variant(button).map(Binding::Gamepad);
+        }
+        parse_variant(input).map(Binding::Key)
+    }
+
+    fn is_gamepad(&self) -> bool {
+        matches!(self, Binding::Gamepad(_) | Binding::GamepadAxis(..))
+    }
+}
+
+// Variant names are case sensitive in RON, so also try capitalising the first letter
+fn parse_variant<T: for<'de> Deserialize<'de>>(name: &str) -> Option<T> {
+    let mut chars = name.chars();
+    let capitalised = match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+        None => return None,
+    };
+    ron::from_str(name)
+        .or_else(|_| ron::from_str(&capitalised))
+        .ok()
+}
+
+// Stick and trigger tuning, stored alongside the bindings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GamepadConfig {
+    // Axis values below the deadzone are ignored, the rest is rescaled to 0..1
+    pub deadzone: f32,
+    // Look speed in radians per second at full stick deflection
+    pub look_sensitivity: f32,
+}
+
+impl Default for GamepadConfig {
+    fn default() -> Self {
+        Self {
+            deadzone: 0.15,
+            look_sensitivity: 2.5,
+        }
+    }
+}
+
+impl GamepadConfig {
+    fn apply_deadzone(&self, value: f32) -> f32 {
+        if value <= self.deadzone {
+            return 0.;
+        }
+        ((value - self.deadzone) / (1. - self.deadzone)).min(1.)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bindings {
+    pub actions: BTreeMap<Action, Vec<Binding>>,
+    #[serde(default)]
+    pub gamepad: GamepadConfig,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let actions = [
+            (
+                Action::MoveForward,
+                vec![
+                    Binding::Key(KeyCode::W),
+                    Binding::GamepadAxis(GamepadAxisType::LeftStickY, AxisDirection::Positive),
+                ],
+            ),
+            (
+                Action::MoveBackward,
+                vec![
+                    Binding::Key(KeyCode::S),
+                    Binding::GamepadAxis(GamepadAxisType::LeftStickY, AxisDirection::Negative),
+                ],
+            ),
+            (
+                Action::MoveLeft,
+                vec![
+                    Binding::Key(KeyCode::A),
+                    Binding::GamepadAxis(GamepadAxisType::LeftStickX, AxisDirection::Negative),
+                ],
+            ),
+            (
+                Action::MoveRight,
+                vec![
+                    Binding::Key(KeyCode::D),
+                    Binding::GamepadAxis(GamepadAxisType::LeftStickX, AxisDirection::Positive),
+                ],
+            ),
+            (
+                Action::MoveUp,
+                vec![
+                    Binding::Key(KeyCode::E),
+                    Binding::Gamepad(GamepadButtonType::RightTrigger2),
+                ],
+            ),
+            (
+                Action::MoveDown,
+                vec![
+                    Binding::Key(KeyCode::Q),
+                    Binding::Gamepad(GamepadButtonType::LeftTrigger2),
+                ],
+            ),
+            (
+                Action::Boost,
+                vec![
+                    Binding::Key(KeyCode::LShift),
+                    Binding::Gamepad(GamepadButtonType::LeftThumb),
+                ],
+            ),
+            (
+                Action::SpeedUp,
+                vec![Binding::Gamepad(GamepadButtonType::DPadUp)],
+            ),
+            (
+                Action::SpeedDown,
+                vec![Binding::Gamepad(GamepadButtonType::DPadDown)],
+            ),
+            (
+                Action::LookLeft,
+                vec![Binding::GamepadAxis(
+                    GamepadAxisType::RightStickX,
+                    AxisDirection::Negative,
+                )],
+            ),
+            (
+                Action::LookRight,
+                vec![Binding::GamepadAxis(
+                    GamepadAxisType::RightStickX,
+                    AxisDirection::Positive,
+                )],
+            ),
+            (
+                Action::LookUp,
+                vec![Binding::GamepadAxis(
+                    GamepadAxisType::RightStickY,
+                    AxisDirection::Positive,
+                )],
+            ),
+            (
+                Action::LookDown,
+                vec![Binding::GamepadAxis(
+                    GamepadAxisType::RightStickY,
+                    AxisDirection::Negative,
+                )],
+            ),
+            (
+                Action::Select,
+                vec![
+                    Binding::Mouse(MouseButton::Left),
+                    Binding::Gamepad(GamepadButtonType::South),
+                ],
+            ),
+            (
+                Action::FrameAll,
+                vec![
+                    Binding::Key(KeyCode::F),
+                    Binding::Gamepad(GamepadButtonType::North),
+                ],
+            ),
+            (Action::SaveBookmark, vec![Binding::Key(KeyCode::F5)]),
+            (Action::RecallBookmark, vec![Binding::Key(KeyCode::F9)]),
+            (
+                Action::ToggleConsole,
+                vec![
+                    Binding::Key(KeyCode::F1),
+                    Binding::Gamepad(GamepadButtonType::Start),
+                ],
+            ),
+            (
+                Action::CancelLoading,
+                vec![
+                    Binding::Key(KeyCode::Space),
+                    Binding::Gamepad(GamepadButtonType::East),
+                ],
+            ),
+            (
+                Action::TogglePicker,
+                vec![
+                    Binding::Key(KeyCode::F2),
+                    Binding::Gamepad(GamepadButtonType::Select),
+                ],
+            ),
+            (Action::ToggleGalaxies, vec![Binding::Key(KeyCode::F3)]),
+            (Action::ToggleStats, vec![Binding::Key(KeyCode::F4)]),
+            (
+                Action::ToggleConstellations,
+                vec![Binding::Key(KeyCode::F6)],
+            ),
+            (Action::MenuUp, vec![Binding::Key(KeyCode::Up)]),
+            (Action::MenuDown, vec![Binding::Key(KeyCode::Down)]),
+            (Action::MenuTick, vec![Binding::Key(KeyCode::Return)]),
+            (Action::PickerLoad, vec![Binding::Key(KeyCode::L)]),
+            (Action::ColorConstellations, vec![Binding::Key(KeyCode::C)]),
+            (Action::Exit, vec![Binding::Key(KeyCode::Escape)]),
+        ];
+        Self {
+            actions: actions.into_iter().collect(),
+            gamepad: GamepadConfig::default(),
+        }
+    }
+}
+
+impl Bindings {
+    // Load bindings from disk, falling back to the defaults for a missing or broken file
+    pub fn load(path: &str) -> Bindings {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(_) => return Bindings::default(),
+        };
+        match ron::from_str::<Bindings>(&content) {
+            Ok(mut bindings) => {
+                // Actions added after the file was written keep their default bindings
+                for (action, default) in Bindings::default().actions {
+                    bindings.actions.entry(action).or_insert(default);
+                }
+                bindings
+            }
+            Err(e) => {
+                println!("[BINDINGS] Failed to parse {}: {}", path, e);
+                Bindings::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let pretty = ron::ser::PrettyConfig::default();
+        let content = ron::ser::to_string_pretty(self, pretty).map_err(|e| e.to_string())?;
+        fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path, e))
+    }
+
+    // Bind an input to an action, taking it away from any action that used it before.
+    //  Replaces the action's previous keyboard/mouse or gamepad inputs, but not both
+    pub fn bind(&mut self, action: Action, binding: Binding) {
+        for bindings in self.actions.values_mut() {
+            bindings.retain(|b| *b != binding);
+        }
+        let bindings = self.actions.entry(action).or_default();
+        bindings.retain(|b| b.is_gamepad() != binding.is_gamepad());
+        bindings.push(binding);
+    }
+}
+
+// Menu panel that takes the menu actions, only one is open at a time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenMenu {
+    None,
+    Datasets,
+    Galaxies,
+    Stats,
+    Constellations,
+}
+
+// Not derived, the compilers Bevy 0.6 builds with can't mark a default variant
+#[allow(clippy::derivable_impls)]
+impl Default for OpenMenu {
+    fn default() -> Self {
+        OpenMenu::None
+    }
+}
+
+impl OpenMenu {
+    // Open a menu in place of the current one, or close it if it is already open
+    pub fn toggle(&mut self, menu: OpenMenu) {
+        *self = if *self == menu { OpenMenu::None } else { menu };
+    }
+}
+
+// Actions triggered in the current frame, resolved from the raw input resources
+#[derive(Default)]
+pub struct Actions {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+    // Analog strength in 0..1, digital inputs count as fully pressed
+    values: HashMap<Action, f32>,
+}
+
+impl Actions {
+    pub fn value(&self, action: Action) -> f32 {
+        self.values.get(&action).copied().unwrap_or(0.)
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+}
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Bindings::load(BINDINGS_PATH))
+            .insert_resource(Actions::default())
+            .init_resource::<OpenMenu>()
+            .add_console_command(
+                CommandSpec::new("bind")
+                    .usage(&[], "List every action and its bindings")
+                    .usage(&[Arg::Text("action")], "Show the bindings of an action")
+                    .usage(
+                        &[Arg::Text("action"), Arg::Text("input")],
+                        "Bind an input (e.g. W, mouse:Left, pad:South, axis:LeftStickX-) to an action",
+                    ),
+            )
+            .add_system_to_stage(CoreStage::PreUpdate, update_actions.after(InputSystem))
+            .add_system(bind_command)
+            .add_system(exit_control);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_actions(
+    bindings: Res<Bindings>,
+    keys: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_button_axes: Res<Axis<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    console_state: Res<State<ConsoleState>>,
+    mut actions: ResMut<Actions>,
+) {
+    // Keys typed into the console shouldn't also fly the camera around
+    let typing = *console_state.current() == ConsoleState::On;
+
+    actions.pressed.clear();
+    actions.just_pressed.clear();
+    actions.values.clear();
+
+    for (action, action_bindings) in bindings.actions.iter() {
+        for binding in action_bindings {
+            let (value, just_pressed) = match *binding {
+                Binding::Key(_)
+                    if typing && !matches!(action, Action::ToggleConsole | Action::Exit) =>
+                {
+                    (0., false)
+                }
+                Binding::Key(key) => (keys.pressed(key) as u8 as f32, keys.just_pressed(key)),
+                Binding::Mouse(button) => (
+                    mouse.pressed(button) as u8 as f32,
+                    mouse.just_pressed(button),
+                ),
+                // Any connected gamepad can trigger the action,
+                //  analog buttons (e.g. triggers) report how far they are pushed
+                Binding::Gamepad(button_type) => gamepads
+                    .iter()
+                    .map(|gamepad| {
+                        let button = GamepadButton(*gamepad, button_type);
+                        let value = match gamepad_button_axes.get(button) {
+                            Some(value) => bindings.gamepad.apply_deadzone(value),
+                            None => gamepad_buttons.pressed(button) as u8 as f32,
+                        };
+                        (value, gamepad_buttons.just_pressed(button))
+                    })
+                    .fold((0f32, false), |(v, jp), (value, just_pressed)| {
+                        (v.max(value), jp || just_pressed)
+                    }),
+                Binding::GamepadAxis(axis_type, direction) => {
+                    let value = gamepads
+                        .iter()
+                        .filter_map(|gamepad| gamepad_axes.get(GamepadAxis(*gamepad, axis_type)))
+                        .map(|value| match direction {
+                            AxisDirection::Positive => value,
+                            AxisDirection::Negative => -value,
+                        })
+                        .map(|value| bindings.gamepad.apply_deadzone(value))
+                        .fold(0., f32::max);
+                    (value, false)
+                }
+            };
+            if value > 0. {
+                actions.pressed.insert(*action);
+                let strongest = actions.value(*action).max(value);
+                actions.values.insert(*action, strongest);
+            }
+            if just_pressed {
+                actions.just_pressed.insert(*action);
+            }
+        }
+    }
+}
+
+// bind <action> [input]
+//  show the bindings of an action, or rebind it and save to disk
+fn bind_command(
+    mut events: EventReader<CommandEvent>,
+    mut bindings: ResMut<Bindings>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    for ev in events.iter().filter(|ev| ev.name == "bind") {
+        let action = match ev.args.first() {
+            Some(name) => match ron::from_str::<Action>(&name.to_lowercase()) {
+                Ok(action) => action,
+                Err(_) => {
+                    log.error(format!("[BINDINGS] Unknown action: {}", name));
+                    continue;
+                }
+            },
+            None => {
+                for (action, action_bindings) in bindings.actions.iter() {
+                    log.info(format!("[BINDINGS] {:?}: {:?}", action, action_bindings));
+                }
+                continue;
+            }
+        };
+
+        match ev.args.get(1) {
+            Some(input) => match Binding::parse(input) {
+                Some(binding) => {
+                    bindings.bind(action, binding);
+                    match bindings.save(BINDINGS_PATH) {
+                        Ok(()) => log.info(format!("[BINDINGS] {:?} -> {:?}", action, binding)),
+                        Err(e) => log.error(format!("[BINDINGS] {}", e)),
+                    }
+                }
+                None => log.error(format!("[BINDINGS] Unknown input: {}", input)),
+            },
+            None => log.info(format!(
+                "[BINDINGS] {:?}: {:?}",
+                action, bindings.actions[&action]
+            )),
+        }
+    }
+}
+
+fn exit_control(actions: Res<Actions>) {
+    if actions.just_pressed(Action::Exit) {
+        std::process::exit(0);
+    }
+}
@@ -0,0 +1,256 @@
+// console variables: tunable values exposed as resources, changed with `set` and saved to disk
+use std::{collections::BTreeMap, fs};
+
+use bevy::prelude::*;
+
+use crate::console::{AddConsoleCommand, Arg, CommandEvent, CommandSpec, ConsoleLog};
+
+const CVARS_PATH: &str = "cvars.ron";
+
+// A value that can be tuned from the console while the app runs.
+//  Each cvar is its own resource, so systems read it like any other resource
+//  and can use change detection or CvarChanged to react when it is set
+pub trait Cvar: Send + Sync + 'static {
+    const NAME: &'static str;
+    const HELP: &'static str;
+    const DEFAULT: f32;
+
+    fn new(value: f32) -> Self;
+    fn get(&self) -> f32;
+}
+
+// Sent whenever a cvar resource takes a new value
+#[derive(Debug, Clone)]
+pub struct CvarChanged {
+    pub name: &'static str,
+    pub value: f32,
+}
+
+struct CvarInfo {
+    help: &'static str,
+    default: f32,
+}
+
+// Every registered cvar and the values set from the console
+#[derive(Default)]
+pub struct Cvars {
+    info: BTreeMap<&'static str, CvarInfo>,
+    // Only values that were loaded or set are kept, the rest use their defaults
+    values: BTreeMap<String, f32>,
+}
+
+impl Cvars {
+    fn load() -> Cvars {
+        let values = match fs::read_to_string(CVARS_PATH) {
+            Ok(content) => ron::from_str(&content).unwrap_or_else(|e| {
+                println!("[CVAR] Ignoring broken {}: {}", CVARS_PATH, e);
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        Cvars {
+            values,
+            ..Default::default()
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let pretty = ron::ser::PrettyConfig::default();
+        let content =
+            ron::ser::to_string_pretty(&self.values, pretty).map_err(|e| e.to_string())?;
+        fs::write(CVARS_PATH, content).map_err(|e| format!("Failed to write {}: {}", CVARS_PATH, e))
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.info.keys().map(|name| name.to_string()).collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<f32> {
+        let info = self.info.get(name)?;
+        Some(self.values.get(name).copied().unwrap_or(info.default))
+    }
+
+    fn describe(&self, name: &str) -> Option<String> {
+        let info = self.info.get(name)?;
+        Some(format!(
+            "[CVAR] {} = {} (default {}) - {}",
+            name,
+            self.get(name)?,
+            info.default,
+            info.help
+        ))
+    }
+}
+
+// All current cvars are sizes, speeds or distances
+fn valid(value: f32) -> bool {
+    value.is_finite() && value > 0.
+}
+
+pub trait AddCvar {
+    fn add_cvar<T: Cvar>(&mut self) -> &mut Self;
+}
+
+impl AddCvar for App {
+    fn add_cvar<T: Cvar>(&mut self) -> &mut Self {
+        let mut cvars = self.world.get_resource_or_insert_with(Cvars::load);
+        cvars.info.insert(
+            T::NAME,
+            CvarInfo {
+                help: T::HELP,
+                default: T::DEFAULT,
+            },
+        );
+        // cvars.ron may have been edited by hand, so hold it to the same check as `set`
+        let loaded = cvars.values.get(T::NAME).copied();
+        if let Some(bad) = loaded.filter(|value| !valid(*value)) {
+            cvars.values.remove(T::NAME);
+            self.world
+                .get_resource_or_insert_with(ConsoleLog::default)
+                .warn(format!(
+                    "[CVAR] Ignoring {} = {} from {}, using the default {}",
+                    T::NAME,
+                    bad,
+                    CVARS_PATH,
+                    T::DEFAULT
+                ));
+        }
+        let value = loaded.filter(|value| valid(*value)).unwrap_or(T::DEFAULT);
+        self.insert_resource(T::new(value))
+            .add_system(apply_cvar::<T>)
+    }
+}
+
+pub struct CvarPlugin;
+
+impl Plugin for CvarPlugin {
+    fn build(&self, app: &mut App) {
+        app.world.get_resource_or_insert_with(Cvars::load);
+        app.add_event::<CvarChanged>()
+            .add_console_command(
+                CommandSpec::new("set")
+                    .usage(&[], "List every cvar and its value")
+                    .usage(&[Arg::Text("name")], "Show the value of a cvar")
+                    .usage(
+                        &[Arg::Text("name"), Arg::Number("value")],
+                        "Change a cvar, saved to cvars.ron",
+                    ),
+            )
+            .add_system(set_command);
+    }
+}
+
+// set
+// set <name>
+// set <name> <value>
+fn set_command(
+    mut events: EventReader<CommandEvent>,
+    mut cvars: ResMut<Cvars>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    for ev in events.iter().filter(|ev| ev.name == "set") {
+        let args: Vec<String> = ev.args.iter().map(|a| a.to_lowercase()).collect();
+        let name = match args.first() {
+            Some(name) if !cvars.info.contains_key(name.as_str()) => {
+                log.error(format!("[CVAR] Unknown cvar: {}", name));
+                continue;
+            }
+            Some(name) => name,
+            None => {
+                for name in cvars.names() {
+                    log.info(cvars.describe(&name).unwrap_or_default());
+                }
+                continue;
+            }
+        };
+        let value = match args.get(1).and_then(|v| v.parse::<f32>().ok()) {
+            Some(value) if valid(value) => value,
+            Some(_) => {
+                log.error(format!("[CVAR] {} must be a positive number", name));
+                continue;
+            }
+            None => {
+                log.info(cvars.describe(name).unwrap_or_default());
+                continue;
+            }
+        };
+
+        cvars.values.insert(name.clone(), value);
+        log.info(format!("[CVAR] {} = {}", name, value));
+        if let Err(e) = cvars.save() {
+            log.error(format!("[CVAR] {}", e));
+        }
+    }
+}
+
+// Copy a newly set value into the cvar's own resource
+fn apply_cvar<T: Cvar>(
+    cvars: Res<Cvars>,
+    mut cvar: ResMut<T>,
+    mut changed_evw: EventWriter<CvarChanged>,
+) {
+    if !cvars.is_changed() {
+        return;
+    }
+    let value = cvars.get(T::NAME).unwrap_or(T::DEFAULT);
+    if value != cvar.get() {
+        *cvar = T::new(value);
+        changed_evw.send(CvarChanged {
+            name: T::NAME,
+            value,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Radius(f32);
+
+    impl Cvar for Radius {
+        const NAME: &'static str = "radius";
+        const HELP: &'static str = "Test radius";
+        const DEFAULT: f32 = 2.;
+
+        fn new(value: f32) -> Self {
+            Radius(value)
+        }
+        fn get(&self) -> f32 {
+            self.0
+        }
+    }
+
+    fn loaded(value: f32) -> App {
+        let mut app = App::new();
+        let mut cvars = Cvars::default();
+        cvars.values.insert("radius".to_string(), value);
+        app.insert_resource(cvars).add_cvar::<Radius>();
+        app
+    }
+
+    #[test]
+    fn valid_loaded_values_are_kept() {
+        let app = loaded(5.);
+        assert_eq!(app.world.get_resource::<Radius>().unwrap().0, 5.);
+        assert!(app.world.get_resource::<ConsoleLog>().is_none());
+    }
+
+    #[test]
+    fn invalid_loaded_values_fall_back_to_the_default() {
+        for bad in [-1., 0., f32::NAN, f32::INFINITY] {
+            let app = loaded(bad);
+            assert_eq!(app.world.get_resource::<Radius>().unwrap().0, 2.);
+            let cvars = app.world.get_resource::<Cvars>().unwrap();
+            assert_eq!(cvars.get("radius"), Some(2.));
+            assert_eq!(
+                app.world
+                    .get_resource::<ConsoleLog>()
+                    .unwrap()
+                    .lines()
+                    .len(),
+                1
+            );
+        }
+    }
+}
@@ -1,12 +1,29 @@
 // debug related controls
 use bevy::{
     diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
+    input::mouse::{MouseScrollUnit, MouseWheel},
     prelude::*,
 };
 
+use crate::{
+    console::{
+        AddConsoleCommand, CommandEvent, CommandHistory, CommandRegistry, CommandSpec, ConsoleLog,
+        LineEditor, PendingCommands, Severity,
+    },
+    controls::{Action, Actions},
+    cvar::Cvars,
+    scene::CartographyRes,
+};
+
 const FONT_SIZE: f32 = 40.;
 const FONT_BOLD: &str = "fonts/FiraSans-Bold.ttf";
 const FONT_MEDIUM: &str = "fonts/FiraMono-Medium.ttf";
+const SCROLLBACK_LINES: usize = 15;
+const SCROLLBACK_FONT_SIZE: f32 = 16.;
+// Lines scrolled per page up/down key press
+const SCROLLBACK_PAGE: usize = SCROLLBACK_LINES - 1;
+// Matches listed in the log when tab completion is ambiguous
+const MAX_COMPLETIONS_SHOWN: usize = 20;
 
 #[derive(Component)]
 struct FPSLabel;
@@ -23,18 +40,13 @@ struct ConsoleLabel;
 #[derive(Component)]
 struct ConsoleCommand;
 
-struct DebugParams {
-    console_input: String,
-    command_queue: Vec<String>,
-}
+#[derive(Component)]
+struct ConsoleScrollback;
 
-impl Default for DebugParams {
-    fn default() -> Self {
-        Self {
-            console_input: String::new(),
-            command_queue: vec![],
-        }
-    }
+#[derive(Default)]
+struct DebugParams {
+    // How many lines the scrollback is scrolled up from the newest line
+    scrollback_offset: usize,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
@@ -44,7 +56,7 @@ enum FPSState {
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
-enum ConsoleState {
+pub enum ConsoleState {
     On,
     Off,
 }
@@ -55,6 +67,7 @@ impl Plugin for DebugPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(FrameTimeDiagnosticsPlugin::default())
             .insert_resource(DebugParams::default())
+            .add_console_command(CommandSpec::new("fps").usage(&[], "Show/hide the FPS counter"))
             .add_startup_system(setup_debug_ui)
             .add_startup_system(setup_fps)
             .add_startup_system(setup_console_window)
@@ -69,13 +82,14 @@ impl Plugin for DebugPlugin {
             .add_system_set(
                 SystemSet::on_update(ConsoleState::On)
                     .with_system(update_console_input)
-                    .with_system(commands_processor),
+                    .with_system(scroll_console_log)
+                    .with_system(update_console_log),
             )
             .add_system_set(
                 SystemSet::on_enter(ConsoleState::Off).with_system(update_console_visibility),
             )
-            .add_system(exit_control)
-            .add_system(toggle_console);
+            .add_system(toggle_console)
+            .add_system(fps_command);
     }
 }
 
@@ -83,14 +97,8 @@ fn setup_debug_ui(mut commands: Commands) {
     commands.spawn_bundle(UiCameraBundle::default());
 }
 
-fn exit_control(input: Res<Input<KeyCode>>) {
-    if input.just_pressed(KeyCode::Escape) {
-        std::process::exit(0);
-    }
-}
-
-fn toggle_console(input: Res<Input<KeyCode>>, mut console_state: ResMut<State<ConsoleState>>) {
-    if input.just_pressed(KeyCode::F1) {
+fn toggle_console(actions: Res<Actions>, mut console_state: ResMut<State<ConsoleState>>) {
+    if actions.just_pressed(Action::ToggleConsole) {
         match console_state.current() {
             ConsoleState::On => {
                 console_state.set(ConsoleState::Off).unwrap();
@@ -170,11 +178,7 @@ fn update_fps_visibility(
     }
 }
 
-fn setup_console_window(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    debug_params: ResMut<DebugParams>,
-) {
+fn setup_console_window(mut commands: Commands, asset_server: Res<AssetServer>) {
     // Draw a console window at bottom of screen like UE4
     // with a prefix '>' on the left
     let window_style = Style {
@@ -231,16 +235,24 @@ fn setup_console_window(
                         .spawn_bundle(TextBundle {
                             style: command_style.clone(),
                             visibility: Visibility { is_visible: false },
-                            text: Text::with_section(
-                                debug_params.console_input.clone(),
-                                TextStyle {
-                                    font_size: 20.,
-                                    color: Color::WHITE,
-                                    font: asset_server.load(FONT_BOLD),
-                                    ..Default::default()
-                                },
-                                Default::default(),
-                            ),
+                            // Text before the caret, the caret, then text after it
+                            text: Text {
+                                sections: ["", "|", ""]
+                                    .iter()
+                                    .map(|value| TextSection {
+                                        value: value.to_string(),
+                                        style: TextStyle {
+                                            font_size: 20.,
+                                            color: match *value {
+                                                "|" => Color::YELLOW,
+                                                _ => Color::WHITE,
+                                            },
+                                            font: asset_server.load(FONT_BOLD),
+                                        },
+                                    })
+                                    .collect(),
+                                ..Default::default()
+                            },
                             ..Default::default()
                         })
                         .insert(Console)
@@ -249,6 +261,45 @@ fn setup_console_window(
         })
         .insert(Console)
         .insert(ConsoleWindow);
+
+    // Scrollback of command output just above the input line
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(30.),
+                    ..Default::default()
+                },
+                size: Size::new(
+                    Val::Percent(100.0),
+                    Val::Px(SCROLLBACK_LINES as f32 * (SCROLLBACK_FONT_SIZE + 4.) + 10.),
+                ),
+                align_items: AlignItems::FlexEnd,
+                ..Default::default()
+            },
+            color: Color::rgba(0.1, 0.1, 0.1, 0.85).into(),
+            visibility: Visibility { is_visible: false },
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect::all(Val::Px(5.)),
+                        ..Default::default()
+                    },
+                    visibility: Visibility { is_visible: false },
+                    text: Text {
+                        sections: vec![],
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .insert(Console)
+                .insert(ConsoleScrollback);
+        })
+        .insert(Console);
 }
 
 fn update_console_visibility(
@@ -269,50 +320,175 @@ fn update_console_visibility(
     }
 }
 
+// Keys that move the caret or recall history, typed characters come from ReceivedCharacter
+#[allow(clippy::too_many_arguments)]
 fn update_console_input(
-    mut debug_params: ResMut<DebugParams>,
+    keys: Res<Input<KeyCode>>,
+    registry: Res<CommandRegistry>,
+    cartography: Res<CartographyRes>,
+    cvars: Res<Cvars>,
+    mut editor: ResMut<LineEditor>,
+    mut history: ResMut<CommandHistory>,
+    mut pending: ResMut<PendingCommands>,
+    mut log: ResMut<ConsoleLog>,
     mut input: EventReader<ReceivedCharacter>,
     mut query: Query<&mut Text, With<ConsoleCommand>>,
 ) {
-    let mut text = query.single_mut();
+    let ctrl = keys.any_pressed([
+        KeyCode::LControl,
+        KeyCode::RControl,
+        KeyCode::LWin,
+        KeyCode::RWin,
+    ]);
+    let mut edited = false;
+
+    for key in keys.get_just_pressed() {
+        edited = true;
+        match key {
+            KeyCode::Left if ctrl => editor.word_left(),
+            KeyCode::Left => editor.left(),
+            KeyCode::Right if ctrl => editor.word_right(),
+            KeyCode::Right => editor.right(),
+            KeyCode::Home => editor.home(),
+            KeyCode::End => editor.end(),
+            KeyCode::Up => {
+                if let Some(line) = history.previous(editor.text()) {
+                    editor.set(line);
+                }
+            }
+            KeyCode::Down => {
+                if let Some(line) = history.next() {
+                    editor.set(line);
+                }
+            }
+            KeyCode::Tab => {
+                let words: Vec<String> = cartography
+                    .planets
+                    .keys()
+                    .chain(cartography.galaxies.keys())
+                    .cloned()
+                    .chain(cvars.names())
+                    .collect();
+                let matches = editor.complete(&registry.names(), &words);
+                if matches.len() > 1 {
+                    let shown = matches.len().min(MAX_COMPLETIONS_SHOWN);
+                    let mut listing = matches[..shown].join("  ");
+                    if matches.len() > shown {
+                        listing += &format!("  ... ({} more)", matches.len() - shown);
+                    }
+                    log.info(listing);
+                }
+            }
+            KeyCode::V if ctrl => match arboard::Clipboard::new().and_then(|mut c| c.get_text()) {
+                // Keep pasted text on one line
+                Ok(pasted) => editor.insert(&pasted.replace(|c: char| c.is_control(), " ")),
+                Err(e) => log.error(format!("Failed to paste: {}", e)),
+            },
+            _ => edited = false,
+        }
+    }
+
     for e in input.iter() {
-        if e.char == '\u{0a}' || e.char == '\u{0d}' {
-            // push new command to queue
-            let command = debug_params.console_input.clone();
-            debug_params.command_queue.push(command);
-            debug_params.console_input.clear();
-        } else if e.char == '\u{8}' {
-            let mut modified = debug_params.console_input.chars();
-            modified.next_back();
-            debug_params.console_input = modified.as_str().to_string();
-        } else {
-            debug_params.console_input += &e.char.to_string();
+        edited = true;
+        match e.char {
+            '\u{0a}' | '\u{0d}' => {
+                // push new command to queue
+                let command = editor.take();
+                if let Err(e) = history.push(&command) {
+                    log.warn(format!("Failed to save command history: {}", e));
+                }
+                pending.push(command);
+            }
+            '\u{8}' if ctrl => editor.delete_word(),
+            '\u{8}' => editor.backspace(),
+            // ctrl+w
+            '\u{17}' => editor.delete_word(),
+            // Delete key, or ctrl+backspace on some platforms
+            '\u{7f}' if keys.pressed(KeyCode::Delete) => editor.delete(),
+            '\u{7f}' => editor.delete_word(),
+            c if c.is_control() => {}
+            c => editor.insert(&c.to_string()),
         }
+    }
 
-        text.sections[0].value = debug_params.console_input.clone();
+    if edited {
+        let mut text = query.single_mut();
+        let (before, after) = editor.text().split_at(editor.caret());
+        text.sections[0].value = before.to_string();
+        text.sections[2].value = after.to_string();
     }
 }
 
-fn commands_processor(
+// Page up/down or the mouse wheel scroll through older output
+fn scroll_console_log(
+    keys: Res<Input<KeyCode>>,
+    log: Res<ConsoleLog>,
+    mut wheel_evr: EventReader<MouseWheel>,
     mut debug_params: ResMut<DebugParams>,
-    mut fps_state: ResMut<State<FPSState>>,
 ) {
-    if debug_params.command_queue.len() > 0 {
-        let command = debug_params.command_queue.first().unwrap().clone();
-        // remove first command in the queue
-        debug_params.command_queue = debug_params.command_queue.drain(1..).collect();
-        // process the command
-        let cmd = command.to_uppercase();
-        match cmd.as_str() {
-            "FPS" => match fps_state.current() {
-                FPSState::Off => {
-                    fps_state.set(FPSState::On).unwrap();
-                }
-                FPSState::On => {
-                    fps_state.set(FPSState::Off).unwrap();
-                }
+    let mut scroll: i64 = 0;
+    if keys.just_pressed(KeyCode::PageUp) {
+        scroll += SCROLLBACK_PAGE as i64;
+    }
+    if keys.just_pressed(KeyCode::PageDown) {
+        scroll -= SCROLLBACK_PAGE as i64;
+    }
+    for ev in wheel_evr.iter() {
+        scroll += match ev.unit {
+            MouseScrollUnit::Line => ev.y.round() as i64,
+            MouseScrollUnit::Pixel => (ev.y / 20.).round() as i64,
+        };
+    }
+    if scroll == 0 {
+        return;
+    }
+    let max_offset = log.lines().len().saturating_sub(SCROLLBACK_LINES) as i64;
+    debug_params.scrollback_offset =
+        (debug_params.scrollback_offset as i64 + scroll).clamp(0, max_offset) as usize;
+}
+
+fn update_console_log(
+    log: Res<ConsoleLog>,
+    debug_params: Res<DebugParams>,
+    asset_server: Res<AssetServer>,
+    mut query: Query<&mut Text, With<ConsoleScrollback>>,
+) {
+    if !log.is_changed() && !debug_params.is_changed() {
+        return;
+    }
+    let mut text = query.single_mut();
+    let lines = log.lines();
+    let end = lines.len().saturating_sub(debug_params.scrollback_offset);
+    let start = end.saturating_sub(SCROLLBACK_LINES);
+
+    let font = asset_server.load(FONT_MEDIUM);
+    text.sections = lines
+        .range(start..end)
+        .map(|(severity, line)| TextSection {
+            value: format!("{}\n", line),
+            style: TextStyle {
+                font_size: SCROLLBACK_FONT_SIZE,
+                font: font.clone(),
+                color: match severity {
+                    Severity::Echo => Color::GRAY,
+                    Severity::Info => Color::WHITE,
+                    Severity::Warning => Color::YELLOW,
+                    Severity::Error => Color::RED,
+                },
             },
-            _ => {}
+        })
+        .collect();
+}
+
+fn fps_command(mut events: EventReader<CommandEvent>, mut fps_state: ResMut<State<FPSState>>) {
+    for ev in events.iter().filter(|ev| ev.name == "fps") {
+        match fps_state.current() {
+            FPSState::Off => {
+                fps_state.set(FPSState::On).unwrap();
+            }
+            FPSState::On => {
+                fps_state.set(FPSState::Off).unwrap();
+            }
         }
     }
 }
@@ -0,0 +1,397 @@
+// hides parts of the universe at runtime without reloading it: whole galaxies,
+//  along with the planets and connections only they contribute, and weak connections
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use crate::{
+    console::{AddConsoleCommand, Arg, CommandEvent, CommandSpec, ConsoleLog},
+    controls::{Action, Actions, OpenMenu},
+    menu::{menu_sections, spawn_menu_panel, PanelAnchor},
+    scene::{CartographyRes, ConnectionComp, PlanetComp},
+};
+
+const FONT_SIZE: f32 = 16.;
+const HELP: &str = "Up/Down: move    Enter: show/hide    F3: close\n\n";
+
+// Galaxies switched off with the galaxy command or menu
+#[derive(Default)]
+pub struct HiddenGalaxies(pub HashSet<String>);
+
+impl HiddenGalaxies {
+    // Planets shared with a galaxy that is still shown stay visible
+    pub fn shows_planet(&self, galaxies: &HashSet<String>) -> bool {
+        galaxies.iter().any(|galaxy| !self.0.contains(galaxy))
+    }
+}
+
+// Connections drawn, set with the edges command
+#[derive(Default)]
+pub struct EdgeFilter {
+    // Fewest times two tags must have been seen together
+    pub min_count: i32,
+    // Most connections kept per planet in each galaxy, the heaviest first
+    pub top_n: Option<usize>,
+}
+
+impl EdgeFilter {
+    fn describe(&self) -> String {
+        let top = match self.top_n {
+            Some(n) => format!("top {} per planet", n),
+            None => "all per planet".to_string(),
+        };
+        format!("count >= {}, {}", self.min_count.max(1), top)
+    }
+
+    // Connections of each galaxy that pass the filter, as (galaxy, planet, neighbour).
+    //  Tag lists are sorted heaviest first when read, so the top ones are at the front
+    fn kept<'a>(&self, cartography: &'a CartographyRes) -> HashSet<(&'a str, &'a str, &'a str)> {
+        let mut kept = HashSet::new();
+        let top_n = self.top_n.unwrap_or(usize::MAX);
+        for (name, galaxy) in cartography.galaxies.iter() {
+            for (tag, connected) in galaxy.relation.relation_map.iter() {
+                for t in connected
+                    .iter()
+                    .take_while(|t| t.count >= self.min_count)
+                    .take(top_n)
+                {
+                    kept.insert((name.as_str(), tag.as_str(), t.name.as_str()));
+                }
+            }
+        }
+        kept
+    }
+}
+
+// Galaxy highlighted in the menu
+#[derive(Default)]
+struct GalaxyMenu {
+    cursor: usize,
+}
+
+pub enum GalaxyRequest {
+    Show(String),
+    Hide(String),
+    // Hide every galaxy but one
+    Solo(String),
+    // Show every galaxy again
+    Reset,
+    List,
+    ToggleMenu,
+    // Move the highlight up or down by a number of galaxies
+    Move(i32),
+    // Show or hide the highlighted galaxy
+    ToggleCursor,
+}
+
+#[derive(Component, Clone)]
+struct GalaxyPanel;
+
+pub struct FilterPlugin;
+
+impl Plugin for FilterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HiddenGalaxies>()
+            .init_resource::<EdgeFilter>()
+            .init_resource::<GalaxyMenu>()
+            .add_event::<GalaxyRequest>()
+            .add_console_command(
+                CommandSpec::new("galaxy")
+                    .usage(&[], "List galaxies and whether they are shown")
+                    .usage(
+                        &[Arg::Literal("show"), Arg::Text("name")],
+                        "Show a hidden galaxy",
+                    )
+                    .usage(
+                        &[Arg::Literal("hide"), Arg::Text("name")],
+                        "Hide a galaxy, planets shared with shown galaxies stay",
+                    )
+                    .usage(
+                        &[Arg::Literal("solo"), Arg::Text("name")],
+                        "Hide every other galaxy",
+                    )
+                    .usage(&[Arg::Literal("reset")], "Show every galaxy"),
+            )
+            .add_console_command(
+                CommandSpec::new("edges")
+                    .usage(&[], "Show which connections are drawn")
+                    .usage(
+                        &[Arg::Literal("min"), Arg::Number("count")],
+                        "Hide connections between tags seen together fewer times",
+                    )
+                    .usage(
+                        &[Arg::Literal("top"), Arg::Number("n")],
+                        "Keep only the heaviest connections of each planet in each galaxy",
+                    )
+                    .usage(&[Arg::Literal("reset")], "Draw every connection"),
+            )
+            .add_startup_system(setup_galaxy_panel)
+            .add_system(galaxy_control)
+            .add_system(galaxy_command)
+            .add_system(handle_galaxies)
+            .add_system(edges_command)
+            .add_system(prune_hidden_galaxies)
+            .add_system(apply_filters)
+            .add_system(update_galaxy_panel);
+    }
+}
+
+fn setup_galaxy_panel(mut commands: Commands, asset_server: Res<AssetServer>) {
+    spawn_menu_panel(
+        &mut commands,
+        &asset_server,
+        PanelAnchor::TopLeft,
+        FONT_SIZE,
+        GalaxyPanel,
+    );
+}
+
+fn galaxy_control(
+    actions: Res<Actions>,
+    menu: Res<OpenMenu>,
+    mut requests: EventWriter<GalaxyRequest>,
+) {
+    if actions.just_pressed(Action::ToggleGalaxies) {
+        requests.send(GalaxyRequest::ToggleMenu);
+    }
+    if *menu != OpenMenu::Galaxies {
+        return;
+    }
+    if actions.just_pressed(Action::MenuUp) {
+        requests.send(GalaxyRequest::Move(-1));
+    }
+    if actions.just_pressed(Action::MenuDown) {
+        requests.send(GalaxyRequest::Move(1));
+    }
+    if actions.just_pressed(Action::MenuTick) {
+        requests.send(GalaxyRequest::ToggleCursor);
+    }
+}
+
+// galaxy
+// galaxy show|hide|solo <name>
+// galaxy reset
+fn galaxy_command(mut events: EventReader<CommandEvent>, mut requests: EventWriter<GalaxyRequest>) {
+    for ev in events.iter().filter(|ev| ev.name == "galaxy") {
+        let args: Vec<&str> = ev.args.iter().map(|a| a.as_str()).collect();
+        match args.as_slice() {
+            [] => requests.send(GalaxyRequest::List),
+            ["show", name] => requests.send(GalaxyRequest::Show(name.to_string())),
+            ["hide", name] => requests.send(GalaxyRequest::Hide(name.to_string())),
+            ["solo", name] => requests.send(GalaxyRequest::Solo(name.to_string())),
+            ["reset"] => requests.send(GalaxyRequest::Reset),
+            _ => {}
+        }
+    }
+}
+
+// Every galaxy name, in the order the menu lists them
+fn galaxy_names(cartography: &CartographyRes) -> Vec<String> {
+    let mut names: Vec<String> = cartography.galaxies.keys().cloned().collect();
+    names.sort();
+    names
+}
+
+fn handle_galaxies(
+    mut requests: EventReader<GalaxyRequest>,
+    mut hidden: ResMut<HiddenGalaxies>,
+    mut galaxy_menu: ResMut<GalaxyMenu>,
+    mut menu: ResMut<OpenMenu>,
+    mut log: ResMut<ConsoleLog>,
+    cartography: Res<CartographyRes>,
+) {
+    let names = galaxy_names(&cartography);
+    for request in requests.iter() {
+        if let GalaxyRequest::Show(name) | GalaxyRequest::Hide(name) | GalaxyRequest::Solo(name) =
+            request
+        {
+            if !cartography.galaxies.contains_key(name) {
+                log.error(format!("[GALAXY] Unknown galaxy: {}", name));
+                continue;
+            }
+        }
+        match request {
+            GalaxyRequest::Show(name) => {
+                hidden.0.remove(name);
+                log.info(format!("[GALAXY] Showing {}", name));
+            }
+            GalaxyRequest::Hide(name) => {
+                hidden.0.insert(name.clone());
+                log.info(format!("[GALAXY] Hiding {}", name));
+            }
+            GalaxyRequest::Solo(name) => {
+                hidden.0 = names.iter().filter(|n| *n != name).cloned().collect();
+                log.info(format!("[GALAXY] Showing only {}", name));
+            }
+            GalaxyRequest::Reset => {
+                hidden.0.clear();
+                log.info("[GALAXY] Showing every galaxy");
+            }
+            GalaxyRequest::List => {
+                for name in names.iter() {
+                    let state = if hidden.0.contains(name) {
+                        "hidden"
+                    } else {
+                        "shown"
+                    };
+                    log.info(format!("[GALAXY] {} ({})", name, state));
+                }
+            }
+            GalaxyRequest::ToggleMenu => menu.toggle(OpenMenu::Galaxies),
+            GalaxyRequest::Move(by) => {
+                let last = names.len().saturating_sub(1) as i32;
+                galaxy_menu.cursor = (galaxy_menu.cursor as i32 + by).clamp(0, last) as usize;
+            }
+            GalaxyRequest::ToggleCursor => {
+                if let Some(name) = names.get(galaxy_menu.cursor) {
+                    if !hidden.0.remove(name) {
+                        hidden.0.insert(name.clone());
+                    }
+                }
+            }
+        }
+    }
+}
+
+// edges
+// edges min <count>
+// edges top <n>
+// edges reset
+fn edges_command(
+    mut events: EventReader<CommandEvent>,
+    mut filter: ResMut<EdgeFilter>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    for ev in events.iter().filter(|ev| ev.name == "edges") {
+        let args: Vec<&str> = ev.args.iter().map(|a| a.as_str()).collect();
+        match args.as_slice() {
+            [] => {}
+            ["min", count] => match count.parse::<i32>() {
+                Ok(count) if count > 0 => filter.min_count = count,
+                _ => {
+                    log.error("[EDGES] The count must be a positive whole number");
+                    continue;
+                }
+            },
+            ["top", n] => match n.parse::<usize>() {
+                Ok(n) if n > 0 => filter.top_n = Some(n),
+                _ => {
+                    log.error("[EDGES] The number of connections must be a positive whole number");
+                    continue;
+                }
+            },
+            ["reset"] => *filter = EdgeFilter::default(),
+            _ => continue,
+        }
+        log.info(format!("[EDGES] {}", filter.describe()));
+    }
+}
+
+// Forget hidden galaxies that are gone after a reload, so they show again if they come back
+fn prune_hidden_galaxies(cartography: Res<CartographyRes>, mut hidden: ResMut<HiddenGalaxies>) {
+    if !cartography.is_changed() {
+        return;
+    }
+    // Only written when a galaxy is gone, writing marks it changed and reapplies the filters
+    if hidden
+        .0
+        .iter()
+        .any(|name| !cartography.galaxies.contains_key(name))
+    {
+        hidden
+            .0
+            .retain(|name| cartography.galaxies.contains_key(name));
+    }
+}
+
+// Show or hide planets and connections whenever the filters change, new ones are spawned
+//  or a reload moves a planet to other galaxies
+fn apply_filters(
+    hidden: Res<HiddenGalaxies>,
+    filter: Res<EdgeFilter>,
+    cartography: Res<CartographyRes>,
+    changed_planets_q: Query<(), Changed<PlanetComp>>,
+    added_conns_q: Query<(), Added<ConnectionComp>>,
+    mut planet_q: Query<(&PlanetComp, &mut Visibility), Without<ConnectionComp>>,
+    mut conn_q: Query<(&ConnectionComp, &mut Visibility), Without<PlanetComp>>,
+) {
+    // Spawned entities only show up once their commands are applied,
+    //  which may be after the pipeline events about them are read
+    let spawned = !changed_planets_q.is_empty() || !added_conns_q.is_empty();
+    if !spawned && !hidden.is_changed() && !filter.is_changed() {
+        return;
+    }
+    for (planet, mut visibility) in planet_q.iter_mut() {
+        visibility.is_visible = hidden.shows_planet(&planet.galaxies);
+    }
+
+    // Only worth building when connections are capped, the count alone is on the component
+    let kept = filter.top_n.map(|_| filter.kept(&cartography));
+    // A connection is drawn while any galaxy whose tags made it is shown and keeps it.
+    //  Capped connections are kept only if they are among the heaviest of both planets,
+    //  so no planet is left with more than the cap
+    for (conn, mut visibility) in conn_q.iter_mut() {
+        let (a, b) = (conn.planets.0.as_str(), conn.planets.1.as_str());
+        visibility.is_visible = conn.galaxies.iter().any(|(galaxy, weight)| {
+            let galaxy = galaxy.as_str();
+            let in_top = match &kept {
+                Some(kept) => kept.contains(&(galaxy, a, b)) && kept.contains(&(galaxy, b, a)),
+                None => true,
+            };
+            !hidden.0.contains(galaxy) && *weight >= filter.min_count && in_top
+        });
+    }
+}
+
+fn update_galaxy_panel(
+    hidden: Res<HiddenGalaxies>,
+    filter: Res<EdgeFilter>,
+    galaxy_menu: Res<GalaxyMenu>,
+    menu: Res<OpenMenu>,
+    cartography: Res<CartographyRes>,
+    mut panel_q: Query<&mut Visibility, With<GalaxyPanel>>,
+    mut text_q: Query<&mut Text, With<GalaxyPanel>>,
+) {
+    if !hidden.is_changed()
+        && !filter.is_changed()
+        && !galaxy_menu.is_changed()
+        && !menu.is_changed()
+        && !cartography.is_changed()
+    {
+        return;
+    }
+    for mut visibility in panel_q.iter_mut() {
+        visibility.is_visible = *menu == OpenMenu::Galaxies;
+    }
+
+    let mut text = text_q.single_mut();
+    let style = text.sections[0].style.clone();
+    let mut rows = vec![];
+    for (index, name) in galaxy_names(&cartography).iter().enumerate() {
+        let planets = cartography
+            .planets
+            .values()
+            .filter(|planet| planet.belong_galaxy.contains(name))
+            .count();
+        let shown = !hidden.0.contains(name);
+        let color = match (index == galaxy_menu.cursor, shown) {
+            (true, _) => Color::YELLOW,
+            (false, true) => Color::WHITE,
+            (false, false) => Color::GRAY,
+        };
+        rows.push((
+            format!(
+                "[{}] {:<20} {:>7} planets\n",
+                if shown { "x" } else { " " },
+                name,
+                planets
+            ),
+            color,
+        ));
+    }
+    rows.push((
+        format!("\nConnections drawn: {}", filter.describe()),
+        Color::GRAY,
+    ));
+    text.sections = menu_sections(&style, HELP, rows);
+}
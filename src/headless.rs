@@ -0,0 +1,231 @@
+// analysis subcommands that print results without opening a window
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+};
+
+use serde::Serialize;
+use sok_cartography::constellation::find_constellations;
+
+use crate::{
+    analysis::{measure_centrality, universe_connections, universe_stats, Centrality},
+    data::DatasetSource,
+    stats::describe_universe,
+    universe::{generate_universe_cartography, Galaxy, Planet},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone)]
+pub enum Command {
+    Stats,
+    Top {
+        domain: String,
+        tag: String,
+        n: usize,
+    },
+    Path {
+        domain: String,
+        from: String,
+        to: String,
+    },
+    // Write the whole universe to a file, or stdout without one
+    Export {
+        output: Option<String>,
+    },
+}
+
+#[derive(Serialize)]
+struct RelatedTag {
+    tag: String,
+    count: i32,
+}
+
+#[derive(Serialize)]
+struct TagPath {
+    tags: Vec<String>,
+    cost: i32,
+}
+
+#[derive(Serialize)]
+struct ExportedPlanet {
+    name: String,
+    galaxies: Vec<String>,
+    centrality: Centrality,
+    constellation: String,
+}
+
+#[derive(Serialize)]
+struct ExportedConnection {
+    from: String,
+    to: String,
+    count: i32,
+}
+
+#[derive(Serialize)]
+struct Export {
+    galaxies: Vec<String>,
+    planets: Vec<ExportedPlanet>,
+    connections: Vec<ExportedConnection>,
+}
+
+// Load the datasets and run a single command, printing its result to stdout
+pub fn run(command: &Command, source: &DatasetSource, format: OutputFormat) -> Result<(), String> {
+    let (galaxies, mut planets, errors) = generate_universe_cartography(source);
+    // stderr, so output on stdout stays clean
+    for e in errors.iter() {
+        eprintln!("[SKIPPED] {}", e);
+    }
+    if galaxies.is_empty() {
+        return Err(format!("No datasets loaded from {}", source));
+    }
+
+    match command {
+        Command::Stats => {
+            let stats = universe_stats(&galaxies, &planets);
+            match format {
+                OutputFormat::Json => print_json(&stats),
+                OutputFormat::Text => {
+                    for line in describe_universe(&stats) {
+                        println!("{}", line);
+                    }
+                    Ok(())
+                }
+            }
+        }
+        Command::Top { domain, tag, n } => {
+            let galaxy = find_galaxy(&galaxies, domain)?;
+            let tags: Vec<RelatedTag> = galaxy
+                .relation
+                .find_top_n(tag, *n)
+                .into_iter()
+                .map(|t| RelatedTag {
+                    tag: t.name,
+                    count: t.count,
+                })
+                .collect();
+            if tags.is_empty() {
+                return Err(format!("No tags connected to {} in {}", tag, domain));
+            }
+            match format {
+                OutputFormat::Json => print_json(&tags),
+                OutputFormat::Text => {
+                    for (i, t) in tags.iter().enumerate() {
+                        println!("{:>3}. {} ({})", i + 1, t.tag, t.count);
+                    }
+                    Ok(())
+                }
+            }
+        }
+        Command::Path { domain, from, to } => {
+            let galaxy = find_galaxy(&galaxies, domain)?;
+            let (hops, cost) = galaxy
+                .relation
+                .find_path(from.clone(), to.clone())
+                .ok_or_else(|| format!("No path from {} to {} in {}", from, to, domain))?;
+            let path = TagPath {
+                tags: hops.into_iter().map(|t| t.name).collect(),
+                cost,
+            };
+            match format {
+                OutputFormat::Json => print_json(&path),
+                OutputFormat::Text => {
+                    println!(
+                        "{} ({} hops, cost {})",
+                        path.tags.join(" -> "),
+                        path.tags.len().saturating_sub(1),
+                        path.cost
+                    );
+                    Ok(())
+                }
+            }
+        }
+        Command::Export { output } => {
+            measure_centrality(&galaxies, &mut planets);
+            find_constellations(&galaxies, &mut planets);
+            let export = export_universe(&galaxies, &planets);
+            let content = match format {
+                OutputFormat::Json => {
+                    serde_json::to_string_pretty(&export).map_err(|e| e.to_string())?
+                }
+                // One connection per line, tab separated
+                OutputFormat::Text => export
+                    .connections
+                    .iter()
+                    .map(|c| format!("{}\t{}\t{}\n", c.from, c.to, c.count))
+                    .collect(),
+            };
+            match output {
+                Some(path) => {
+                    fs::write(path, content).map_err(|e| format!("{}: {}", path, e))?;
+                    eprintln!(
+                        "[EXPORT] {} planets, {} connections to {}",
+                        export.planets.len(),
+                        export.connections.len(),
+                        path
+                    );
+                    Ok(())
+                }
+                None => {
+                    print!("{}", content);
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
+fn find_galaxy<'a>(
+    galaxies: &'a HashMap<String, Galaxy>,
+    domain: &str,
+) -> Result<&'a Galaxy, String> {
+    galaxies
+        .get(domain)
+        .ok_or_else(|| format!("Unknown domain: {}", domain))
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{}", json);
+    Ok(())
+}
+
+fn export_universe(
+    galaxies: &HashMap<String, Galaxy>,
+    planets: &HashMap<String, Planet>,
+) -> Export {
+    // Sorted so exports of the same datasets are identical
+    let planets: BTreeMap<&String, &Planet> = planets.iter().collect();
+    let mut galaxy_names: Vec<String> = galaxies.keys().cloned().collect();
+    galaxy_names.sort();
+
+    Export {
+        galaxies: galaxy_names,
+        planets: planets
+            .values()
+            .map(|planet| {
+                let mut galaxies: Vec<String> = planet.belong_galaxy.iter().cloned().collect();
+                galaxies.sort();
+                ExportedPlanet {
+                    name: planet.name.clone(),
+                    galaxies,
+                    centrality: planet.centrality.unwrap_or_default(),
+                    constellation: planet.constellation.clone().unwrap_or_default(),
+                }
+            })
+            .collect(),
+        // Pairs connected by several galaxies are exported once, with their heaviest count
+        connections: universe_connections(galaxies)
+            .into_iter()
+            .map(|((from, to), count)| ExportedConnection {
+                from: from.to_string(),
+                to: to.to_string(),
+                count,
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,186 @@
+// watches the dataset files and rebuilds the universe when they are added, removed or changed
+use std::{
+    collections::HashMap,
+    fs,
+    sync::{
+        mpsc::{channel, Receiver},
+        Mutex,
+    },
+    time::SystemTime,
+};
+
+use bevy::{asset::AssetPath, prelude::*, tasks::AsyncComputeTaskPool};
+
+use crate::{
+    console::ConsoleLog,
+    data::{load_relation, DatasetSource, LoadError, MetaRelation},
+    loading::{AppState, DatasetHandles},
+    relation_asset::{AssetRoot, RelationAsset},
+};
+
+// How often dataset files are checked for changes
+const POLL_SECONDS: f32 = 1.;
+
+// Changed files and what reading them again gave
+type Reread = Vec<(String, Result<MetaRelation, LoadError>)>;
+
+struct DatasetWatch {
+    // When each dataset file was last modified
+    modified: HashMap<String, SystemTime>,
+    timer: Timer,
+    // Changed files being read on the task pool
+    receiver: Option<Mutex<Receiver<Reread>>>,
+}
+
+impl Default for DatasetWatch {
+    fn default() -> Self {
+        Self {
+            modified: HashMap::new(),
+            timer: Timer::from_seconds(POLL_SECONDS, true),
+            receiver: None,
+        }
+    }
+}
+
+fn modified_times(source: &DatasetSource) -> HashMap<String, SystemTime> {
+    source
+        .paths()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|path| {
+            let modified = fs::metadata(&path).and_then(|meta| meta.modified()).ok()?;
+            Some((path, modified))
+        })
+        .collect()
+}
+
+pub struct HotReloadPlugin;
+
+impl Plugin for HotReloadPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DatasetWatch>()
+            .add_system_set(SystemSet::on_enter(AppState::Loading).with_system(snapshot_datasets))
+            .add_system_set(
+                SystemSet::on_update(AppState::Running)
+                    .with_system(watch_datasets)
+                    .with_system(apply_reloaded),
+            );
+    }
+}
+
+// Remember the files as they were when loading started. Later changes to DatasetSource
+//  aren't snapshot, so the files they add or remove are picked up like any other change
+fn snapshot_datasets(source: Res<DatasetSource>, mut watch: ResMut<DatasetWatch>) {
+    watch.modified = modified_times(&source);
+}
+
+// Bevy 0.6 only reloads assets from its own watcher, which can't watch files outside assets/,
+//  so files are polled and the changed ones read again on the task pool
+fn watch_datasets(
+    time: Res<Time>,
+    pool: Res<AsyncComputeTaskPool>,
+    source: Res<DatasetSource>,
+    mut watch: ResMut<DatasetWatch>,
+    mut datasets: ResMut<DatasetHandles>,
+    mut log: ResMut<ConsoleLog>,
+) {
+    if !watch.timer.tick(time.delta()).just_finished()
+        || watch.receiver.is_some()
+        || datasets.waiting
+    {
+        return;
+    }
+    let modified = modified_times(&source);
+    let changed: Vec<String> = modified
+        .iter()
+        .filter(|(path, time)| watch.modified.get(*path) != Some(time))
+        .map(|(path, _)| path.clone())
+        .collect();
+    let removed: Vec<String> = watch
+        .modified
+        .keys()
+        .filter(|path| !modified.contains_key(*path))
+        .cloned()
+        .collect();
+    if changed.is_empty() && removed.is_empty() {
+        return;
+    }
+    watch.modified = modified;
+
+    for path in removed {
+        log.info(format!("[DATASET] Removed {}", path));
+        datasets.files.retain(|(file, _)| *file != path);
+        datasets.unreadable.remove(&path);
+    }
+    if changed.is_empty() {
+        datasets.waiting = true;
+        return;
+    }
+
+    for path in changed.iter() {
+        log.info(format!("[DATASET] Reloading {}", path));
+    }
+    let (sender, receiver) = channel();
+    watch.receiver = Some(Mutex::new(receiver));
+    pool.spawn(async move {
+        let reread = changed
+            .into_iter()
+            .map(|path| {
+                let result = load_relation(&path);
+                (path, result)
+            })
+            .collect();
+        let _ = sender.send(reread);
+    })
+    .detach();
+}
+
+// Replace the changed relation assets and rebuild the universe from every file
+#[allow(clippy::too_many_arguments)]
+fn apply_reloaded(
+    asset_server: Res<AssetServer>,
+    asset_root: Res<AssetRoot>,
+    source: Res<DatasetSource>,
+    mut watch: ResMut<DatasetWatch>,
+    mut relations: ResMut<Assets<RelationAsset>>,
+    mut datasets: ResMut<DatasetHandles>,
+) {
+    let reread = match &watch.receiver {
+        Some(receiver) => match receiver.lock().unwrap().try_recv() {
+            Ok(reread) => reread,
+            Err(_) => return,
+        },
+        None => return,
+    };
+    watch.receiver = None;
+
+    for (path, result) in reread {
+        let handle = match datasets.files.iter().find(|(file, _)| *file == path) {
+            Some((_, handle)) => handle.clone(),
+            None => {
+                let handle: Handle<RelationAsset> =
+                    asset_server.get_handle(AssetPath::from(asset_root.asset_path(&path)));
+                datasets.files.push((path.clone(), handle.clone()));
+                handle
+            }
+        };
+        match result {
+            Ok(relation) => {
+                relations.set_untracked(&handle, RelationAsset(relation));
+                datasets.unreadable.remove(&path);
+            }
+            // Read again for the reason along with the other skipped files
+            Err(_) => {
+                relations.remove(&handle);
+                datasets.unreadable.insert(path);
+            }
+        }
+    }
+
+    // Keep files in the order they are listed, duplicate domains are resolved by it
+    let order = source.paths().unwrap_or_default();
+    datasets
+        .files
+        .sort_by_key(|(path, _)| order.iter().position(|listed| listed == path));
+    datasets.waiting = true;
+}
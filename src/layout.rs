@@ -0,0 +1,216 @@
+// planet positions cached on disk so the universe looks the same between runs
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    str::FromStr,
+};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+// Shared by everything persisted alongside the layout (e.g. bookmarks)
+pub const CACHE_DIR: &str = "cache/";
+const LAYOUT_CACHE_FILE: &str = "layout.ron";
+// Planets are placed inside a cube of this size
+const RANDOM_SPACE_LIMIT: f32 = 6000.;
+// How far planets spread around their galaxy's centre in the galaxy layout
+const GALAXY_SPREAD: f32 = 1200.;
+
+// How new planet positions are chosen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LayoutAlgorithm {
+    // Anywhere in space
+    Random,
+    // Clustered around a centre per galaxy, shared planets sit between their galaxies
+    Galaxy,
+}
+
+impl FromStr for LayoutAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "random" => Ok(LayoutAlgorithm::Random),
+            "galaxy" => Ok(LayoutAlgorithm::Galaxy),
+            _ => Err(format!("Unknown layout algorithm: {}", s)),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct LayoutOptions {
+    pub algorithm: LayoutAlgorithm,
+    // Seed for reproducible layouts, random otherwise.
+    //  Cached positions are kept, so it only reproduces a layout generated from scratch
+    pub seed: Option<u64>,
+    // Ignore cached positions and lay out every planet again
+    pub regenerate: bool,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        Self {
+            algorithm: LayoutAlgorithm::Random,
+            seed: None,
+            regenerate: false,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LayoutCache {
+    // planet name -> position, kept sorted so the file diffs nicely when shared
+    pub positions: BTreeMap<String, [f32; 3]>,
+}
+
+impl LayoutCache {
+    pub fn load() -> LayoutCache {
+        let path = format!("{}{}", CACHE_DIR, LAYOUT_CACHE_FILE);
+        match fs::read_to_string(&path) {
+            Ok(content) => ron::from_str(&content).unwrap_or_else(|e| {
+                println!("[LAYOUT] Ignoring broken cache {}: {}", path, e);
+                LayoutCache::default()
+            }),
+            Err(_) => LayoutCache::default(),
+        }
+    }
+
+    pub fn save(&self) {
+        let path = format!("{}{}", CACHE_DIR, LAYOUT_CACHE_FILE);
+        if let Err(e) = write_cache_file(&path, self) {
+            println!("[LAYOUT] Failed to write {}: {}", path, e);
+        }
+    }
+}
+
+// Fill in positions for planets (name -> galaxies) the cache doesn't know about yet
+pub fn layout_planets(
+    layout: &mut LayoutCache,
+    planets: &HashMap<String, HashSet<String>>,
+    options: &LayoutOptions,
+) {
+    if options.regenerate {
+        layout.positions.clear();
+    }
+    let mut rng = match options.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    // Sorted so a seed always gives the same layout, whatever the hash order
+    let mut names: Vec<&String> = planets.keys().collect();
+    names.sort();
+    let mut galaxies: Vec<&String> = planets.values().flatten().collect();
+    galaxies.sort();
+    galaxies.dedup();
+    // Galaxies with cached planets keep the centre those are around,
+    //  so planets added by a reload join their siblings
+    let mut cached: HashMap<&String, ([f32; 3], usize)> = HashMap::new();
+    for name in names.iter() {
+        let home = &planets[*name];
+        if let (1, Some(position)) = (home.len(), layout.positions.get(*name)) {
+            let (sum, count) = cached.entry(home.iter().next().unwrap()).or_default();
+            for (axis, value) in position.iter().enumerate() {
+                sum[axis] += value;
+            }
+            *count += 1;
+        }
+    }
+    let centres: HashMap<&String, [f32; 3]> = galaxies
+        .into_iter()
+        .map(|galaxy| match cached.get(galaxy) {
+            Some((sum, count)) => (galaxy, sum.map(|value| value / *count as f32)),
+            None => (galaxy, random_position(&mut rng)),
+        })
+        .collect();
+
+    for name in names {
+        let position = match options.algorithm {
+            LayoutAlgorithm::Random => random_position(&mut rng),
+            LayoutAlgorithm::Galaxy => {
+                let home = &planets[name];
+                let mut position = [0.; 3];
+                for galaxy in home {
+                    for (axis, value) in centres[galaxy].iter().enumerate() {
+                        position[axis] += value / home.len() as f32;
+                    }
+                }
+                position.map(|value| value + rng.gen_range(-GALAXY_SPREAD..GALAXY_SPREAD))
+            }
+        };
+        layout.positions.entry(name.clone()).or_insert(position);
+    }
+}
+
+fn random_position(rng: &mut StdRng) -> [f32; 3] {
+    [0; 3].map(|_| rng.gen_range(0.0..RANDOM_SPACE_LIMIT))
+}
+
+// Serialize to RON and write into the cache directory, creating it if needed
+pub fn write_cache_file<T: Serialize>(path: &str, value: &T) -> Result<(), String> {
+    let content = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+        .map_err(|e| e.to_string())?;
+    fs::create_dir_all(CACHE_DIR).map_err(|e| e.to_string())?;
+    fs::write(path, content).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn homes(planets: &[(&str, &[&str])]) -> HashMap<String, HashSet<String>> {
+        planets
+            .iter()
+            .map(|(name, galaxies)| {
+                let galaxies = galaxies.iter().map(|g| g.to_string()).collect();
+                (name.to_string(), galaxies)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn new_planets_join_their_cached_galaxy() {
+        let options = LayoutOptions {
+            algorithm: LayoutAlgorithm::Galaxy,
+            seed: Some(1),
+            regenerate: false,
+        };
+        let mut layout = LayoutCache::default();
+        layout.positions.insert("bash".into(), [100., 100., 100.]);
+        layout.positions.insert("zsh".into(), [300., 100., 100.]);
+        let planets = homes(&[
+            ("bash", &["unix"]),
+            ("zsh", &["unix"]),
+            ("fish", &["unix"]),
+            ("excel", &["superuser", "unix"]),
+        ]);
+        // Whatever the seed picks for the centres of galaxies without cached planets
+        for seed in 0..10 {
+            let mut layout = LayoutCache {
+                positions: layout.positions.clone(),
+            };
+            let options = LayoutOptions {
+                seed: Some(seed),
+                ..options.clone()
+            };
+            layout_planets(&mut layout, &planets, &options);
+            assert_eq!(layout.positions["bash"], [100., 100., 100.]);
+            let fish = layout.positions["fish"];
+            for (value, centre) in fish.iter().zip([200., 100., 100.]) {
+                assert!((value - centre).abs() <= GALAXY_SPREAD);
+            }
+        }
+
+        // Laid out from scratch, the same seed gives the same layout
+        let regenerate = LayoutOptions {
+            regenerate: true,
+            ..options
+        };
+        let mut first = LayoutCache::default();
+        let mut second = LayoutCache::default();
+        layout_planets(&mut first, &planets, &regenerate);
+        layout_planets(&mut second, &planets, &regenerate);
+        assert_eq!(first.positions, second.positions);
+        layout_planets(&mut layout, &planets, &regenerate);
+        assert_eq!(layout.positions, first.positions);
+    }
+}
@@ -0,0 +1,368 @@
+// loads datasets through the asset server and lays out the universe in the background,
+//  with a progress screen
+use std::{
+    collections::HashSet,
+    sync::{
+        mpsc::{channel, Receiver},
+        Mutex,
+    },
+};
+
+use bevy::{asset::LoadState, prelude::*, tasks::AsyncComputeTaskPool};
+
+use crate::{
+    controls::{Action, Actions},
+    data::{load_relation, DatasetSource, LoadError, LoadReport},
+    layout::{layout_planets, LayoutCache, LayoutOptions},
+    pipeline::{LabelsSpawned, PositionsComputed, UniverseLoaded},
+    relation_asset::{AssetRoot, RelationAsset},
+    scene::{CartographyRes, LoadErrors},
+    universe::build_universe,
+};
+
+const FONT_PATH: &str = "fonts/FiraSans-Bold.ttf";
+const FONT_SIZE: f32 = 24.;
+const PROGRESS_BAR_WIDTH: f32 = 500.;
+const PROGRESS_BAR_HEIGHT: f32 = 16.;
+
+// Loading datasets -> laying out and spawning planets -> running the universe
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum AppState {
+    Loading,
+    Layout,
+    Running,
+}
+
+// Sent from the background task as work gets done
+enum Progress {
+    Loaded(Box<(CartographyRes, LoadErrors)>),
+    LaidOut(LayoutCache),
+}
+
+// The background task currently running, if any
+#[derive(Default)]
+struct BackgroundTask {
+    receiver: Option<Mutex<Receiver<Progress>>>,
+}
+
+// Dataset files requested from the asset server, in the order they were listed
+#[derive(Default)]
+pub struct DatasetHandles {
+    pub files: Vec<(String, Handle<RelationAsset>)>,
+    // Why the dataset directory itself could not be listed
+    pub error: Option<LoadError>,
+    // Files read again outside the asset server that could not be loaded
+    pub unreadable: HashSet<String>,
+    // Set while the universe should be rebuilt once every file is read
+    pub waiting: bool,
+    // Set to stop waiting for the remaining files and go on with those loaded so far
+    cancelled: bool,
+}
+
+#[derive(Component)]
+struct LoadingScreen;
+
+#[derive(Component)]
+struct LoadingText;
+
+#[derive(Component)]
+struct ProgressBar;
+
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(BackgroundTask::default())
+            .init_resource::<DatasetHandles>()
+            .init_resource::<LayoutOptions>()
+            .add_state(AppState::Loading)
+            .add_system_set(
+                SystemSet::on_enter(AppState::Loading)
+                    .with_system(setup_loading_screen)
+                    .with_system(start_loading),
+            )
+            .add_system_set(SystemSet::on_update(AppState::Loading).with_system(cancel_loading))
+            .add_system_set(SystemSet::on_update(AppState::Layout).with_system(finish_loading))
+            // Also run while exploring, when reloaded datasets go through the same stages
+            .add_system(track_datasets)
+            .add_system(start_layout)
+            .add_system(update_progress)
+            .add_system_set(
+                SystemSet::on_enter(AppState::Running).with_system(cleanup_loading_screen),
+            );
+    }
+}
+
+fn setup_loading_screen(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let text_style = TextStyle {
+        font: asset_server.load(FONT_PATH),
+        font_size: FONT_SIZE,
+        color: Color::WHITE,
+    };
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                // Children are laid out bottom up
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            color: Color::rgb(0.1, 0.1, 0.1).into(),
+            ..Default::default()
+        })
+        .insert(LoadingScreen)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect::all(Val::Px(10.)),
+                        ..Default::default()
+                    },
+                    text: Text::with_section(
+                        "Loading datasets",
+                        text_style.clone(),
+                        Default::default(),
+                    ),
+                    ..Default::default()
+                })
+                .insert(LoadingText);
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(PROGRESS_BAR_WIDTH), Val::Px(PROGRESS_BAR_HEIGHT)),
+                        ..Default::default()
+                    },
+                    color: Color::GRAY.into(),
+                    ..Default::default()
+                })
+                .with_children(|bar| {
+                    bar.spawn_bundle(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Percent(0.), Val::Percent(100.)),
+                            ..Default::default()
+                        },
+                        color: Color::GOLD.into(),
+                        ..Default::default()
+                    })
+                    .insert(ProgressBar);
+                });
+            parent.spawn_bundle(TextBundle {
+                style: Style {
+                    margin: Rect::all(Val::Px(10.)),
+                    ..Default::default()
+                },
+                text: Text::with_section(
+                    "Space: skip remaining files    Esc: quit",
+                    TextStyle {
+                        color: Color::GRAY,
+                        font_size: FONT_SIZE * 0.75,
+                        ..text_style
+                    },
+                    Default::default(),
+                ),
+                ..Default::default()
+            });
+        });
+}
+
+// Ask the asset server for every dataset file, they are read in parallel
+fn start_loading(
+    asset_server: Res<AssetServer>,
+    asset_root: Res<AssetRoot>,
+    source: Res<DatasetSource>,
+    mut datasets: ResMut<DatasetHandles>,
+) {
+    *datasets = match source.paths() {
+        Ok(paths) => DatasetHandles {
+            files: paths
+                .into_iter()
+                .map(|path| {
+                    let handle = asset_server.load(asset_root.asset_path(&path));
+                    (path, handle)
+                })
+                .collect(),
+            waiting: true,
+            ..Default::default()
+        },
+        Err(e) => DatasetHandles {
+            error: Some(e),
+            waiting: true,
+            ..Default::default()
+        },
+    };
+}
+
+fn cancel_loading(actions: Res<Actions>, mut datasets: ResMut<DatasetHandles>) {
+    if actions.just_pressed(Action::CancelLoading) {
+        datasets.cancelled = true;
+    }
+}
+
+// Show how many files are read, then build the universe from them on the task pool
+fn track_datasets(
+    pool: Res<AsyncComputeTaskPool>,
+    asset_server: Res<AssetServer>,
+    relations: Res<Assets<RelationAsset>>,
+    mut datasets: ResMut<DatasetHandles>,
+    mut task: ResMut<BackgroundTask>,
+    mut text_q: Query<&mut Text, With<LoadingText>>,
+    mut bar_q: Query<&mut Style, With<ProgressBar>>,
+) {
+    if !datasets.waiting {
+        return;
+    }
+    let datasets = &mut *datasets;
+    // Loaded files only show up in Assets a frame after their load state says so
+    let done = |path: &String, handle: &Handle<RelationAsset>| {
+        relations.get(handle).is_some()
+            || asset_server.get_load_state(handle) == LoadState::Failed
+            || datasets.unreadable.contains(path)
+    };
+    let total = datasets.files.len();
+    let pending: Vec<&String> = datasets
+        .files
+        .iter()
+        .filter(|(path, handle)| !done(path, handle))
+        .map(|(path, _)| path)
+        .collect();
+    if let Some(path) = pending.first() {
+        let read = total - pending.len();
+        if let (Ok(mut text), Ok(mut bar)) = (text_q.get_single_mut(), bar_q.get_single_mut()) {
+            text.sections[0].value = format!("Loading {} ({}/{})", path, read + 1, total);
+            bar.size.width = Val::Percent(100. * read as f32 / total as f32);
+        }
+        if !datasets.cancelled {
+            return;
+        }
+        println!("[LOADING] Skipped {} remaining files", pending.len());
+    }
+
+    // Added in the order files were listed, so duplicate domains are resolved the same way
+    let mut report = LoadReport::default();
+    if let Some(e) = datasets.error.take() {
+        report.errors.push(e);
+    }
+    // Skipped files are forgotten, so waiting for reloads doesn't wait for them
+    datasets.files.retain(|(path, handle)| done(path, handle));
+    for (path, handle) in datasets.files.iter() {
+        match relations.get(handle) {
+            Some(relation) => report.add(path, Ok(relation.0.clone())),
+            // The asset server only logs why a file failed, read it again for the reason
+            None => report.add(path, load_relation(path)),
+        }
+    }
+    datasets.waiting = false;
+    datasets.cancelled = false;
+
+    let (sender, receiver) = channel();
+    task.receiver = Some(Mutex::new(receiver));
+    pool.spawn(async move {
+        let (galaxies, planets) = build_universe(report.relations);
+        let loaded = (
+            CartographyRes { planets, galaxies },
+            LoadErrors(report.errors),
+        );
+        let _ = sender.send(Progress::Loaded(Box::new(loaded)));
+    })
+    .detach();
+}
+
+// Position planets on the task pool once datasets are loaded, reusing the layout cache
+fn start_layout(
+    mut loaded_evr: EventReader<UniverseLoaded>,
+    pool: Res<AsyncComputeTaskPool>,
+    cartography: Res<CartographyRes>,
+    mut layout_options: ResMut<LayoutOptions>,
+    mut task: ResMut<BackgroundTask>,
+) {
+    if loaded_evr.iter().count() == 0 {
+        return;
+    }
+    let (sender, receiver) = channel();
+    task.receiver = Some(Mutex::new(receiver));
+
+    let homes = cartography
+        .planets
+        .iter()
+        .map(|(name, planet)| (name.clone(), planet.belong_galaxy.clone()))
+        .collect();
+    let options = layout_options.clone();
+    // Only the first layout may ignore the cache, reloaded datasets keep planets in place
+    layout_options.regenerate = false;
+    pool.spawn(async move {
+        // reuse the cached positions so views & bookmarks stay valid between runs,
+        //  only planets new to the cache are laid out
+        let mut layout = LayoutCache::load();
+        layout_planets(&mut layout, &homes, &options);
+        layout.save();
+        let _ = sender.send(Progress::LaidOut(layout));
+    })
+    .detach();
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_progress(
+    mut task: ResMut<BackgroundTask>,
+    mut app_state: ResMut<State<AppState>>,
+    mut cartography: ResMut<CartographyRes>,
+    mut load_errors: ResMut<LoadErrors>,
+    mut layout: ResMut<LayoutCache>,
+    mut loaded_evw: EventWriter<UniverseLoaded>,
+    mut positions_evw: EventWriter<PositionsComputed>,
+    mut text_q: Query<&mut Text, With<LoadingText>>,
+    mut bar_q: Query<&mut Style, With<ProgressBar>>,
+) {
+    let messages: Vec<Progress> = match &task.receiver {
+        Some(receiver) => receiver.lock().unwrap().try_iter().collect(),
+        None => return,
+    };
+
+    for message in messages {
+        match message {
+            // Replaced in place rather than inserted with commands,
+            //  so the next stage sees them as soon as it gets the event
+            Progress::Loaded(loaded) => {
+                (*cartography, *load_errors) = *loaded;
+                if let (Ok(mut text), Ok(mut bar)) =
+                    (text_q.get_single_mut(), bar_q.get_single_mut())
+                {
+                    text.sections[0].value =
+                        format!("Laying out {} planets", cartography.planets.len());
+                    bar.size.width = Val::Percent(100.);
+                }
+                task.receiver = None;
+                loaded_evw.send(UniverseLoaded);
+                if *app_state.current() == AppState::Loading {
+                    app_state.set(AppState::Layout).unwrap();
+                }
+            }
+            Progress::LaidOut(laid_out) => {
+                *layout = laid_out;
+                if let Ok(mut text) = text_q.get_single_mut() {
+                    text.sections[0].value = "Spawning planets".to_string();
+                }
+                task.receiver = None;
+                positions_evw.send(PositionsComputed);
+            }
+        }
+    }
+}
+
+// Leave the loading screen once the last stage of the scene is in place
+fn finish_loading(
+    mut labels_evr: EventReader<LabelsSpawned>,
+    mut app_state: ResMut<State<AppState>>,
+) {
+    if labels_evr.iter().count() > 0 {
+        app_state.set(AppState::Running).unwrap();
+    }
+}
+
+fn cleanup_loading_screen(mut commands: Commands, screen_q: Query<Entity, With<LoadingScreen>>) {
+    for screen in screen_q.iter() {
+        commands.entity(screen).despawn_recursive();
+    }
+}
@@ -1,15 +1,48 @@
 #![allow(unused)]
 
 use bevy::prelude::*;
+use bookmark::BookmarkPlugin;
 use camera::CameraPlugin;
+use centrality::CentralityPlugin;
+use console::ConsolePlugin;
+use constellation::ConstellationPlugin;
+use controls::ControlsPlugin;
+use cvar::CvarPlugin;
 use debug::DebugPlugin;
+use filter::FilterPlugin;
+use hot_reload::HotReloadPlugin;
+use loading::LoadingPlugin;
+use picker::PickerPlugin;
+use pipeline::PipelinePlugin;
+use query::QueryPlugin;
+use relation_asset::RelationAssetPlugin;
 use scene::ScenePlugin;
+use sok_cartography::{analysis, data, universe};
+use stats::StatsPlugin;
+use tour::TourPlugin;
 
+mod bookmark;
 mod camera;
-mod data;
+mod centrality;
+mod cli;
+mod console;
+mod constellation;
+mod controls;
+mod cvar;
 mod debug;
+mod filter;
+mod headless;
+mod hot_reload;
+mod layout;
+mod loading;
+mod menu;
+mod picker;
+mod pipeline;
+mod query;
+mod relation_asset;
 mod scene;
-mod universe;
+mod stats;
+mod tour;
 
 struct WinSize {
     pub w: f32,
@@ -18,18 +51,47 @@ struct WinSize {
 
 // Generate an interconnected universe of stack exchange using Bevy 3D
 fn main() {
+    let options = cli::parse();
+    if let Some(command) = &options.command {
+        if let Err(e) = headless::run(command, &options.datasets, options.format) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     App::new()
         .insert_resource(Msaa { samples: 4 })
         .insert_resource(ClearColor(Color::rgb(0.4, 0.4, 0.4)))
+        .insert_resource(options.scripts)
+        .insert_resource(options.datasets)
+        .insert_resource(options.browse)
+        .insert_resource(options.layout)
         .insert_resource(WindowDescriptor {
             title: "Stack Exchange Cartography".to_string(),
-            width: 3440.0 / 2.,
-            height: 1440.0 / 2.,
+            width: options.width,
+            height: options.height,
+            mode: options.window_mode,
             ..Default::default()
         })
         .add_plugins(DefaultPlugins)
+        .add_plugin(ConsolePlugin)
+        .add_plugin(CvarPlugin)
+        .add_plugin(ControlsPlugin)
+        .add_plugin(RelationAssetPlugin)
+        .add_plugin(PipelinePlugin)
+        .add_plugin(LoadingPlugin)
+        .add_plugin(HotReloadPlugin)
         .add_plugin(ScenePlugin)
+        .add_plugin(QueryPlugin)
         .add_plugin(CameraPlugin)
+        .add_plugin(BookmarkPlugin)
+        .add_plugin(FilterPlugin)
+        .add_plugin(PickerPlugin)
+        .add_plugin(StatsPlugin)
+        .add_plugin(CentralityPlugin)
+        .add_plugin(ConstellationPlugin)
+        .add_plugin(TourPlugin)
         .add_plugin(DebugPlugin)
         .add_startup_system(setup_window_size)
         .run();
@@ -0,0 +1,92 @@
+// the dark panels menus and planet info are shown in
+use bevy::prelude::*;
+
+pub const FONT_PATH: &str = "fonts/FiraMono-Medium.ttf";
+const PANEL_COLOR: Color = Color::rgba(0.05, 0.05, 0.05, 0.85);
+// Distance of panels from the top of the window, below the FPS counter
+const PANEL_TOP: f32 = 40.;
+const PANEL_MARGIN: f32 = 10.;
+
+// Window corner a panel is shown in
+pub enum PanelAnchor {
+    TopLeft,
+    TopRight,
+}
+
+// Spawn a hidden panel with an empty text in it, both tagged with the marker:
+//  query Visibility with it to show the panel, and Text to fill it in
+pub fn spawn_menu_panel(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    anchor: PanelAnchor,
+    font_size: f32,
+    marker: impl Component + Clone,
+) -> Entity {
+    let position = match anchor {
+        PanelAnchor::TopLeft => Rect {
+            top: Val::Px(PANEL_TOP),
+            left: Val::Px(PANEL_MARGIN),
+            ..Default::default()
+        },
+        PanelAnchor::TopRight => Rect {
+            top: Val::Px(PANEL_TOP),
+            right: Val::Px(PANEL_MARGIN),
+            ..Default::default()
+        },
+    };
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position,
+                padding: Rect::all(Val::Px(PANEL_MARGIN)),
+                ..Default::default()
+            },
+            color: PANEL_COLOR.into(),
+            visibility: Visibility { is_visible: false },
+            ..Default::default()
+        })
+        .insert(marker.clone())
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font: asset_server.load(FONT_PATH),
+                            font_size,
+                            color: Color::WHITE,
+                        },
+                        Default::default(),
+                    ),
+                    visibility: Visibility { is_visible: false },
+                    ..Default::default()
+                })
+                .insert(marker);
+        })
+        .id()
+}
+
+// Key help in gray, followed by one section per row in its own color
+pub fn menu_sections(
+    style: &TextStyle,
+    help: &str,
+    rows: impl IntoIterator<Item = (String, Color)>,
+) -> Vec<TextSection> {
+    let help = TextSection {
+        value: help.to_string(),
+        style: TextStyle {
+            color: Color::GRAY,
+            ..style.clone()
+        },
+    };
+    std::iter::once(help)
+        .chain(rows.into_iter().map(|(value, color)| TextSection {
+            value,
+            style: TextStyle {
+                color,
+                ..style.clone()
+            },
+        }))
+        .collect()
+}
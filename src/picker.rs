@@ -0,0 +1,342 @@
+// a menu of every dataset file that could be loaded, to pick galaxies without restarting
+use std::{
+    collections::HashSet,
+    fs,
+    sync::{
+        mpsc::{channel, Receiver},
+        Mutex,
+    },
+};
+
+use bevy::{prelude::*, tasks::AsyncComputeTaskPool};
+
+use crate::{
+    console::{AddConsoleCommand, Arg, CommandEvent, CommandSpec, ConsoleLog},
+    controls::{Action, Actions, OpenMenu},
+    data::{domain_of, DatasetSource, MetaRelation},
+    loading::DatasetHandles,
+    menu::{menu_sections, spawn_menu_panel, PanelAnchor},
+};
+
+const FONT_SIZE: f32 = 16.;
+const HELP: &str = "Up/Down: move    Enter: tick    L: load ticked    F2: close\n\n";
+
+// Directories listed in the picker besides the loaded datasets
+pub struct BrowseDirs(pub Vec<String>);
+
+// A dataset file found in one of the browsed directories
+#[derive(Debug, Clone)]
+struct PickerEntry {
+    path: String,
+    domain: String,
+    size: u64,
+    // How many tags it has, or why it can't be loaded
+    tags: Result<usize, String>,
+    ticked: bool,
+}
+
+impl PickerEntry {
+    fn describe(&self) -> String {
+        let tags = match &self.tags {
+            Ok(tags) => format!("{} tags", tags),
+            Err(_) => "unloadable".to_string(),
+        };
+        format!(
+            "[{}] {:<20} {:>9} {:>11}  {}",
+            if self.ticked { "x" } else { " " },
+            self.domain,
+            format_size(self.size),
+            tags,
+            self.path
+        )
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        self.domain == name || self.path == name
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1048575 => format!("{:.1} KB", bytes as f64 / 1024.),
+        _ => format!("{:.1} MB", bytes as f64 / 1048576.),
+    }
+}
+
+#[derive(Default)]
+struct Picker {
+    entries: Vec<PickerEntry>,
+    // Entry highlighted in the menu
+    cursor: usize,
+    // Files being measured on the task pool
+    receiver: Option<Mutex<Receiver<Vec<PickerEntry>>>>,
+}
+
+pub enum PickerRequest {
+    Toggle,
+    // Move the highlight up or down by a number of entries
+    Move(i32),
+    // Flip the highlighted entry
+    TickCursor,
+    // Tick or untick an entry by domain or path
+    Tick(String, bool),
+    List,
+    // Rebuild the universe from the ticked files
+    Load,
+}
+
+#[derive(Component, Clone)]
+struct PickerPanel;
+
+pub struct PickerPlugin;
+
+impl Plugin for PickerPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Picker::default())
+            .add_event::<PickerRequest>()
+            .add_console_command(
+                CommandSpec::new("datasets")
+                    .usage(&[], "List dataset files that can be loaded")
+                    .usage(
+                        &[Arg::Literal("tick"), Arg::Text("name")],
+                        "Tick a dataset file by domain or path",
+                    )
+                    .usage(
+                        &[Arg::Literal("untick"), Arg::Text("name")],
+                        "Untick a dataset file by domain or path",
+                    )
+                    .usage(
+                        &[Arg::Literal("load")],
+                        "Rebuild the universe from the ticked files",
+                    ),
+            )
+            .add_startup_system(setup_picker)
+            .add_system(picker_control)
+            .add_system(datasets_command)
+            .add_system(handle_picker)
+            .add_system(receive_scan)
+            .add_system(update_picker_panel);
+    }
+}
+
+fn setup_picker(mut commands: Commands, asset_server: Res<AssetServer>) {
+    spawn_menu_panel(
+        &mut commands,
+        &asset_server,
+        PanelAnchor::TopLeft,
+        FONT_SIZE,
+        PickerPanel,
+    );
+}
+
+fn picker_control(
+    actions: Res<Actions>,
+    menu: Res<OpenMenu>,
+    mut requests: EventWriter<PickerRequest>,
+) {
+    if actions.just_pressed(Action::TogglePicker) {
+        requests.send(PickerRequest::Toggle);
+    }
+    if *menu != OpenMenu::Datasets {
+        return;
+    }
+    if actions.just_pressed(Action::MenuUp) {
+        requests.send(PickerRequest::Move(-1));
+    }
+    if actions.just_pressed(Action::MenuDown) {
+        requests.send(PickerRequest::Move(1));
+    }
+    if actions.just_pressed(Action::MenuTick) {
+        requests.send(PickerRequest::TickCursor);
+    }
+    if actions.just_pressed(Action::PickerLoad) {
+        requests.send(PickerRequest::Load);
+    }
+}
+
+// datasets
+// datasets tick <name>
+// datasets untick <name>
+// datasets load
+fn datasets_command(
+    mut events: EventReader<CommandEvent>,
+    mut requests: EventWriter<PickerRequest>,
+) {
+    for ev in events.iter().filter(|ev| ev.name == "datasets") {
+        let args: Vec<&str> = ev.args.iter().map(|a| a.as_str()).collect();
+        match args.as_slice() {
+            [] => requests.send(PickerRequest::List),
+            ["tick", name] => requests.send(PickerRequest::Tick(name.to_string(), true)),
+            ["untick", name] => requests.send(PickerRequest::Tick(name.to_string(), false)),
+            ["load"] => requests.send(PickerRequest::Load),
+            _ => {}
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_picker(
+    mut requests: EventReader<PickerRequest>,
+    mut picker: ResMut<Picker>,
+    mut menu: ResMut<OpenMenu>,
+    mut source: ResMut<DatasetSource>,
+    mut log: ResMut<ConsoleLog>,
+    pool: Res<AsyncComputeTaskPool>,
+    browse: Res<BrowseDirs>,
+    datasets: Res<DatasetHandles>,
+) {
+    for request in requests.iter() {
+        match request {
+            PickerRequest::Toggle => {
+                menu.toggle(OpenMenu::Datasets);
+                if *menu == OpenMenu::Datasets {
+                    start_scan(&mut picker, &pool, &browse, &source, &datasets);
+                }
+            }
+            PickerRequest::Move(by) => {
+                let last = picker.entries.len().saturating_sub(1) as i32;
+                picker.cursor = (picker.cursor as i32 + by).clamp(0, last) as usize;
+            }
+            PickerRequest::TickCursor => {
+                let cursor = picker.cursor;
+                if let Some(entry) = picker.entries.get_mut(cursor) {
+                    entry.ticked = !entry.ticked;
+                }
+            }
+            PickerRequest::Tick(name, ticked) => {
+                if picker.entries.is_empty() {
+                    start_scan(&mut picker, &pool, &browse, &source, &datasets);
+                    log.warn("[DATASET] Still looking for dataset files, try again in a moment");
+                    continue;
+                }
+                let mut found = false;
+                for entry in picker.entries.iter_mut().filter(|e| e.matches(name)) {
+                    entry.ticked = *ticked;
+                    found = true;
+                }
+                if !found {
+                    log.error(format!("[DATASET] No dataset file named {}", name));
+                }
+            }
+            PickerRequest::List => {
+                if picker.entries.is_empty() {
+                    start_scan(&mut picker, &pool, &browse, &source, &datasets);
+                    log.warn("[DATASET] Still looking for dataset files, try again in a moment");
+                    continue;
+                }
+                for entry in picker.entries.iter() {
+                    log.info(entry.describe());
+                }
+            }
+            PickerRequest::Load => {
+                let ticked: Vec<String> = picker
+                    .entries
+                    .iter()
+                    .filter(|e| e.ticked)
+                    .map(|e| e.path.clone())
+                    .collect();
+                if ticked.is_empty() {
+                    log.error("[DATASET] Tick at least one dataset file to load");
+                    continue;
+                }
+                log.info(format!("[DATASET] Loading {} files", ticked.len()));
+                // The dataset watcher notices the files that were added or removed
+                //  and rebuilds the universe in place
+                *source = DatasetSource::Files(ticked);
+                if *menu == OpenMenu::Datasets {
+                    *menu = OpenMenu::None;
+                }
+            }
+        }
+    }
+}
+
+// Measure every file in the browsed directories and the current source on the task pool
+fn start_scan(
+    picker: &mut Picker,
+    pool: &AsyncComputeTaskPool,
+    browse: &BrowseDirs,
+    source: &DatasetSource,
+    datasets: &DatasetHandles,
+) {
+    if picker.receiver.is_some() {
+        return;
+    }
+    let mut paths: Vec<String> = source.paths().unwrap_or_default();
+    for dir in browse.0.iter() {
+        paths.extend(DatasetSource::Dir(dir.clone()).paths().unwrap_or_default());
+    }
+    let mut seen = HashSet::new();
+    paths.retain(|path| seen.insert(path.clone()));
+    let loaded: Vec<String> = datasets
+        .files
+        .iter()
+        .map(|(path, _)| path.clone())
+        .collect();
+
+    let (sender, receiver) = channel();
+    picker.receiver = Some(Mutex::new(receiver));
+    pool.spawn(async move {
+        let entries = paths
+            .into_iter()
+            .map(|path| {
+                let domain = domain_of(&path);
+                PickerEntry {
+                    size: fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0),
+                    tags: MetaRelation::new(domain.clone(), path.clone())
+                        .map(|relation| relation.relation_map.len())
+                        .map_err(|e| e.to_string()),
+                    ticked: loaded.contains(&path),
+                    domain,
+                    path,
+                }
+            })
+            .collect();
+        let _ = sender.send(entries);
+    })
+    .detach();
+}
+
+fn receive_scan(mut picker: ResMut<Picker>) {
+    let entries = match &picker.receiver {
+        Some(receiver) => match receiver.lock().unwrap().try_recv() {
+            Ok(entries) => entries,
+            Err(_) => return,
+        },
+        None => return,
+    };
+    picker.receiver = None;
+    picker.cursor = picker.cursor.min(entries.len().saturating_sub(1));
+    picker.entries = entries;
+}
+
+fn update_picker_panel(
+    picker: Res<Picker>,
+    menu: Res<OpenMenu>,
+    mut panel_q: Query<&mut Visibility, With<PickerPanel>>,
+    mut text_q: Query<&mut Text, With<PickerPanel>>,
+) {
+    if !picker.is_changed() && !menu.is_changed() {
+        return;
+    }
+    for mut visibility in panel_q.iter_mut() {
+        visibility.is_visible = *menu == OpenMenu::Datasets;
+    }
+
+    let mut text = text_q.single_mut();
+    let style = text.sections[0].style.clone();
+    let mut rows = vec![];
+    if picker.entries.is_empty() {
+        rows.push(("Looking for dataset files...".to_string(), Color::WHITE));
+    }
+    for (index, entry) in picker.entries.iter().enumerate() {
+        let color = match (&entry.tags, index == picker.cursor) {
+            (_, true) => Color::YELLOW,
+            (Err(_), false) => Color::GRAY,
+            (Ok(_), false) => Color::WHITE,
+        };
+        rows.push((format!("{}\n", entry.describe()), color));
+    }
+    text.sections = menu_sections(&style, HELP, rows);
+}
@@ -0,0 +1,32 @@
+// stages of building the scene from the datasets, each announced with an event
+//  so the next stage runs after it. New stages (e.g. clustering) can listen for one
+//  event and send the next, and every stage runs again whenever the datasets change:
+//
+//   UniverseLoaded -> PositionsComputed -> PlanetsSpawned -> ConnectionsSpawned -> LabelsSpawned
+use bevy::prelude::*;
+
+// CartographyRes holds newly loaded datasets
+pub struct UniverseLoaded;
+
+// LayoutCache has a position for every planet
+pub struct PositionsComputed;
+
+// Planets are spawned and indexed by name
+pub struct PlanetsSpawned;
+
+pub struct ConnectionsSpawned;
+
+// Everything is in place, the universe is ready to explore
+pub struct LabelsSpawned;
+
+pub struct PipelinePlugin;
+
+impl Plugin for PipelinePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<UniverseLoaded>()
+            .add_event::<PositionsComputed>()
+            .add_event::<PlanetsSpawned>()
+            .add_event::<ConnectionsSpawned>()
+            .add_event::<LabelsSpawned>();
+    }
+}
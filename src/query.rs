@@ -0,0 +1,101 @@
+// console commands querying the tag relations behind the universe
+use bevy::prelude::*;
+
+use crate::{
+    console::{AddConsoleCommand, Arg, CommandEvent, CommandSpec, ConsoleLog},
+    scene::CartographyRes,
+};
+
+const DEFAULT_TOP_N: usize = 10;
+
+pub struct QueryPlugin;
+
+impl Plugin for QueryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_console_command(
+            CommandSpec::new("top")
+                .usage(
+                    &[Arg::Text("domain"), Arg::Text("tag")],
+                    "List the tags most often used with a tag",
+                )
+                .usage(
+                    &[Arg::Text("domain"), Arg::Text("tag"), Arg::Number("n")],
+                    "List the n tags most often used with a tag",
+                ),
+        )
+        .add_console_command(CommandSpec::new("path").usage(
+            &[Arg::Text("domain"), Arg::Text("from"), Arg::Text("to")],
+            "Find the strongest chain of tags between two tags",
+        ))
+        .add_system(top_command)
+        .add_system(path_command);
+    }
+}
+
+// top <domain> <tag> [n]
+fn top_command(
+    mut events: EventReader<CommandEvent>,
+    mut log: ResMut<ConsoleLog>,
+    cartography: Res<CartographyRes>,
+) {
+    for ev in events.iter().filter(|ev| ev.name == "top") {
+        let (domain, tag) = (&ev.args[0], &ev.args[1]);
+        let n = match ev.args.get(2) {
+            Some(n) => n.parse::<f64>().unwrap_or(0.).max(0.) as usize,
+            None => DEFAULT_TOP_N,
+        };
+        let galaxy = match cartography.galaxies.get(domain) {
+            Some(galaxy) => galaxy,
+            None => {
+                log.error(format!("[QUERY] Unknown domain: {}", domain));
+                continue;
+            }
+        };
+
+        let tags = galaxy.relation.find_top_n(tag, n);
+        if tags.is_empty() {
+            log.warn(format!(
+                "[QUERY] No tags connected to {} in {}",
+                tag, domain
+            ));
+            continue;
+        }
+        for (i, t) in tags.iter().enumerate() {
+            log.info(format!("{:>3}. {} ({})", i + 1, t.name, t.count));
+        }
+    }
+}
+
+// path <domain> <from> <to>
+fn path_command(
+    mut events: EventReader<CommandEvent>,
+    mut log: ResMut<ConsoleLog>,
+    cartography: Res<CartographyRes>,
+) {
+    for ev in events.iter().filter(|ev| ev.name == "path") {
+        let (domain, from, to) = (&ev.args[0], &ev.args[1], &ev.args[2]);
+        let galaxy = match cartography.galaxies.get(domain) {
+            Some(galaxy) => galaxy,
+            None => {
+                log.error(format!("[QUERY] Unknown domain: {}", domain));
+                continue;
+            }
+        };
+
+        match galaxy.relation.find_path(from.clone(), to.clone()) {
+            Some((hops, cost)) => {
+                let names: Vec<&str> = hops.iter().map(|t| t.name.as_str()).collect();
+                log.info(format!(
+                    "{} ({} hops, cost {})",
+                    names.join(" -> "),
+                    hops.len().saturating_sub(1),
+                    cost
+                ));
+            }
+            None => log.warn(format!(
+                "[QUERY] No path from {} to {} in {}",
+                from, to, domain
+            )),
+        }
+    }
+}
@@ -0,0 +1,73 @@
+// relation JSON files as assets, so datasets load through the AssetServer like fonts do
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    asset::{AssetLoader, AssetServerSettings, BoxedFuture, FileAssetIo, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+};
+
+use crate::data::{domain_of, MetaRelation};
+
+#[derive(Debug, Clone, TypeUuid)]
+#[uuid = "5b9a3c1e-8f0d-4a6e-b2c7-3d41e9f7a820"]
+pub struct RelationAsset(pub MetaRelation);
+
+// Reads `<domain>.json` files in the same format as `MetaRelation::new`
+#[derive(Default)]
+pub struct RelationLoader;
+
+impl AssetLoader for RelationLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let path = load_context.path().to_string_lossy().to_string();
+            let relation = MetaRelation::from_slice(domain_of(&path), path, bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(RelationAsset(relation)));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["json"]
+    }
+}
+
+// Where the asset server reads from, with symlinks resolved
+pub struct AssetRoot(pub PathBuf);
+
+impl FromWorld for AssetRoot {
+    fn from_world(world: &mut World) -> Self {
+        let settings = world.get_resource_or_insert_with(AssetServerSettings::default);
+        let root = FileAssetIo::get_root_path().join(&settings.asset_folder);
+        AssetRoot(fs::canonicalize(&root).unwrap_or(root))
+    }
+}
+
+impl AssetRoot {
+    // Asset paths are relative to the assets folder,
+    //  datasets anywhere else are loaded from their absolute path
+    pub fn asset_path(&self, path: &str) -> PathBuf {
+        let absolute = fs::canonicalize(path).unwrap_or_else(|_| Path::new(path).to_path_buf());
+        match absolute.strip_prefix(&self.0) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => absolute,
+        }
+    }
+}
+
+pub struct RelationAssetPlugin;
+
+impl Plugin for RelationAssetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<RelationAsset>()
+            .init_asset_loader::<RelationLoader>()
+            .init_resource::<AssetRoot>();
+    }
+}
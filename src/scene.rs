@@ -1,48 +1,136 @@
-use std::{collections::HashMap, fmt::Debug};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Debug,
+};
 
 use bevy::prelude::*;
-use pathfinding::prelude::directions::W;
-use rand::{thread_rng, Rng};
 
 const FONT_SIZE_DEFAULT: f32 = 20.;
 const RANDOM_SPACE_LIMIT: f32 = 6000.;
+// Planet meshes are built at this size and scaled to the planet_radius cvar
 const PLANET_RADIUS: f32 = 50.;
 const PLANET_SUBDIVISIONS: usize = 1;
-const FONT_PATH: &str = "fonts/FiraMono-Medium.ttf";
 const FONT_COLOR: Color = Color::GOLD;
+const PLANET_COLOR: Color = Color::WHITE;
+const PLANET_SELECTED_COLOR: Color = Color::ORANGE_RED;
+// Colors of the least and most central planets when planets are emphasized
+const PLANET_PERIPHERAL_COLOR: Color = Color::rgb(0.2, 0.3, 0.8);
+const PLANET_CENTRAL_COLOR: Color = Color::rgb(1., 0.95, 0.6);
+// Size and label distance of the least and most central planets, relative to the others
+const EMPHASIS_MIN_SCALE: f32 = 0.5;
+const EMPHASIS_MAX_SCALE: f32 = 2.;
 const LABEL_FADE_DISTANCE: f32 = 4000.;
 const CONN_MAX_WIDTH: f32 = 20.;
 const CONN_MIN_WIDTH: f32 = 0.2;
+const WARNING_FONT_SIZE: f32 = 18.;
+// How long the list of dataset files that failed to load stays on screen
+const LOAD_WARNING_SECONDS: f32 = 15.;
 
 use crate::{
     camera::SceneCam,
-    universe::{generate_universe_cartography, Galaxy, Planet},
+    console::ConsoleLog,
+    controls::{Action, Actions},
+    cvar::{AddCvar, Cvar, CvarChanged},
+    data::{DatasetSource, LoadError},
+    layout::LayoutCache,
+    menu::FONT_PATH,
+    pipeline::{ConnectionsSpawned, LabelsSpawned, PlanetsSpawned, PositionsComputed},
+    universe::{Galaxy, Planet},
     WinSize,
 };
 
 use bevy_render::camera::Camera;
 
-// we need to query entities during a startup system
-//  so we use this trick to call the startup system
-//   at runtime
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
-enum PlanetConnInitState {
-    Todo,
-    Done,
+#[derive(Component)]
+pub struct PlanetComp {
+    pub name: String,
+    pub galaxies: HashSet<String>,
+    // How central the planet is from 0 to 1 when planets are emphasized by centrality
+    pub emphasis: Option<f32>,
+    // Color of its constellation when planets are colored by constellation
+    pub constellation_color: Option<Color>,
 }
 
 #[derive(Component)]
-struct PlanetComp;
+struct PlanetLabel;
 
 #[derive(Component)]
-struct PlanetLabel;
+struct LoadWarning {
+    // Seconds since startup when it was shown
+    shown_at: f64,
+}
+
+// Dataset files skipped while building the universe
+#[derive(Default)]
+pub struct LoadErrors(pub Vec<LoadError>);
 
 #[derive(Component)]
-struct ConnectionComp;
+pub struct ConnectionComp {
+    pub planets: (String, String),
+    // Heaviest weight of the galaxies below, which sets the width
+    pub weight: i32,
+    // Weight of the connection in each galaxy whose tags made it
+    pub galaxies: BTreeMap<String, i32>,
+}
+
+#[derive(Default)]
+pub struct CartographyRes {
+    pub planets: HashMap<String, Planet>,
+    pub galaxies: HashMap<String, Galaxy>,
+}
+
+pub struct PlanetRadius(pub f32);
+
+impl Cvar for PlanetRadius {
+    const NAME: &'static str = "planet_radius";
+    const HELP: &'static str = "Size of every planet";
+    const DEFAULT: f32 = PLANET_RADIUS;
+
+    fn new(value: f32) -> Self {
+        Self(value)
+    }
+
+    fn get(&self) -> f32 {
+        self.0
+    }
+}
+
+pub struct LabelFadeDistance(pub f32);
+
+impl Cvar for LabelFadeDistance {
+    const NAME: &'static str = "label_fade_distance";
+    const HELP: &'static str = "Planet names further away than this are hidden";
+    const DEFAULT: f32 = LABEL_FADE_DISTANCE;
+
+    fn new(value: f32) -> Self {
+        Self(value)
+    }
+
+    fn get(&self) -> f32 {
+        self.0
+    }
+}
+
+pub struct ConnMaxWidth(pub f32);
+
+impl Cvar for ConnMaxWidth {
+    const NAME: &'static str = "conn_max_width";
+    const HELP: &'static str = "Width of the heaviest connections";
+    const DEFAULT: f32 = CONN_MAX_WIDTH;
+
+    fn new(value: f32) -> Self {
+        Self(value)
+    }
 
-struct CartographyRes {
-    planets: HashMap<String, Planet>,
-    galaxies: HashMap<String, Galaxy>,
+    fn get(&self) -> f32 {
+        self.0
+    }
+}
+
+// Planet currently picked with the select action
+#[derive(Default)]
+pub struct Selection {
+    pub planet: Option<String>,
 }
 
 #[derive(Debug)]
@@ -54,18 +142,27 @@ struct Index {
 pub struct ScenePlugin;
 impl Plugin for ScenePlugin {
     fn build(&self, app: &mut App) {
-        let (galaxies, planets) = generate_universe_cartography();
-        app.insert_resource(CartographyRes { planets, galaxies })
+        // Filled in by the loading screen
+        app.init_resource::<CartographyRes>()
+            .init_resource::<LoadErrors>()
+            .init_resource::<LayoutCache>()
+            .init_resource::<DatasetSource>()
             .insert_resource(Index {
                 label_to_planet: HashMap::new(),
                 name_to_planet: HashMap::new(),
             })
-            .add_startup_system(setup_planets)
-            .add_state(PlanetConnInitState::Todo)
-            .add_system_set(
-                SystemSet::on_enter(PlanetConnInitState::Todo)
-                    .with_system(setup_planetary_connections),
-            )
+            .insert_resource(Selection::default())
+            .add_cvar::<PlanetRadius>()
+            .add_cvar::<LabelFadeDistance>()
+            .add_cvar::<ConnMaxWidth>()
+            .add_system(spawn_planets)
+            .add_system(spawn_connections)
+            .add_system(spawn_labels)
+            .add_system(report_load_errors)
+            .add_system(select_planet)
+            .add_system(highlight_selection)
+            .add_system(resize_scene)
+            .add_system(hide_load_warning)
             .add_system(update_text_position)
             .add_system(update_text_visibility);
         // .add_system(update_text_scale); // <- Too laggy, need to optimize performance first
@@ -82,9 +179,16 @@ fn update_text_position(
 ) {
     for (camera, cam_transform) in camera_q.iter() {
         for (text_entity, mut style, mut visibility) in text_q.iter_mut() {
-            // Use index to query planet by its text label for performance saving
-            let planet_entity = index.label_to_planet.get(&text_entity).unwrap();
-            let planet_transform = planet_q.get(*planet_entity).unwrap();
+            // Use index to query planet by its text label for performance saving,
+            //  labels of planets removed by a reload are only despawned a frame later
+            let planet_transform = match index
+                .label_to_planet
+                .get(&text_entity)
+                .and_then(|planet_entity| planet_q.get(*planet_entity).ok())
+            {
+                Some(planet_transform) => planet_transform,
+                None => continue,
+            };
 
             if let Some(coords) =
                 camera.world_to_screen(&windows, cam_transform, planet_transform.translation)
@@ -107,16 +211,28 @@ fn update_text_position(
 fn update_text_visibility(
     windows: Res<Windows>,
     mut text_q: Query<(Entity, &mut Text, &mut Visibility), With<PlanetLabel>>,
-    planet_q: Query<&GlobalTransform, With<PlanetComp>>,
+    planet_q: Query<(&PlanetComp, &GlobalTransform, &Visibility), Without<PlanetLabel>>,
     camera_q: Query<(&Camera, &GlobalTransform), With<SceneCam>>,
     index: Res<Index>,
     win_size: Res<WinSize>,
+    fade_distance: Res<LabelFadeDistance>,
 ) {
     // Update text label scale to reflect distance
     for (camera, cam_transform) in camera_q.iter() {
         for (text_entity, mut text, mut text_visibility) in text_q.iter_mut() {
-            let planet_entity = index.label_to_planet.get(&text_entity).unwrap();
-            let planet_transform = planet_q.get(*planet_entity).unwrap();
+            let (planet, planet_transform, planet_visibility) = match index
+                .label_to_planet
+                .get(&text_entity)
+                .and_then(|planet_entity| planet_q.get(*planet_entity).ok())
+            {
+                Some(planet) => planet,
+                None => continue,
+            };
+            // Planets of hidden galaxies keep their labels hidden too
+            if !planet_visibility.is_visible {
+                text_visibility.is_visible = false;
+                continue;
+            }
 
             // As text is on the UI,
             // need the corresponding planetary data for distance calculation
@@ -127,8 +243,9 @@ fn update_text_visibility(
             if let Some(coords) =
                 camera.world_to_screen(&windows, cam_transform, planet_transform.translation)
             {
-                // if planet not in sight or over fade distance, hide
-                if dist >= LABEL_FADE_DISTANCE
+                // if planet not in sight or over fade distance, hide.
+                //  Labels of central planets stay visible further away
+                if dist >= fade_distance.0 * emphasis_scale(planet.emphasis)
                     || (coords.y > win_size.h * 2.
                         || coords.y < -win_size.h * 2.
                         || coords.x > win_size.w * 2.
@@ -152,8 +269,14 @@ fn update_text_scale(
     // Update text label scale to reflect distance
     for (camera, cam_transform) in camera_q.iter() {
         for (text_entity, mut text) in text_q.iter_mut() {
-            let planet_entity = index.label_to_planet.get(&text_entity).unwrap();
-            let planet_transform = planet_q.get(*planet_entity).unwrap();
+            let planet_transform = match index
+                .label_to_planet
+                .get(&text_entity)
+                .and_then(|planet_entity| planet_q.get(*planet_entity).ok())
+            {
+                Some(planet_transform) => planet_transform,
+                None => continue,
+            };
 
             // As text is on the UI,
             // need the corresponding planetary data for distance calculation
@@ -168,41 +291,343 @@ fn update_text_scale(
     }
 }
 
-fn setup_planets(
+// Select the planet closest to the centre of the screen
+fn select_planet(
+    actions: Res<Actions>,
+    mut selection: ResMut<Selection>,
+    mut log: ResMut<ConsoleLog>,
+    planet_radius: Res<PlanetRadius>,
+    planet_q: Query<(&PlanetComp, &Transform, &Visibility)>,
+    camera_q: Query<&Transform, With<SceneCam>>,
+) {
+    if !actions.just_pressed(Action::Select) {
+        return;
+    }
+    let cam_tf = camera_q.single();
+    let forward = cam_tf.forward();
+
+    // Find the nearest planet along the view ray, with a bit of slack around its radius
+    let mut nearest: Option<(&str, f32)> = None;
+    for (planet, planet_tf, _) in planet_q.iter().filter(|(_, _, v)| v.is_visible) {
+        let to_planet = planet_tf.translation - cam_tf.translation;
+        let along = to_planet.dot(forward);
+        // Planets sized by their centrality are picked by the size they are drawn at
+        let slack = planet_radius.0 * emphasis_scale(planet.emphasis) * 1.5;
+        if along <= 0. || (to_planet - forward * along).length() > slack {
+            continue;
+        }
+        if !matches!(nearest, Some((_, d)) if d <= along) {
+            nearest = Some((&planet.name, along));
+        }
+    }
+    selection.planet = nearest.map(|(name, _)| name.to_string());
+
+    if let Some(name) = &selection.planet {
+        log.info(format!("[SELECTED] {}", name));
+    }
+}
+
+// Recolor planets whenever the selection changes, wherever it was changed from
+fn highlight_selection(
+    selection: Res<Selection>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    planet_q: Query<(&PlanetComp, &Handle<StandardMaterial>)>,
+) {
+    if !selection.is_changed() {
+        return;
+    }
+    for (planet, material) in planet_q.iter() {
+        let selected = Some(&planet.name) == selection.planet.as_ref();
+        if let Some(material) = materials.get_mut(material) {
+            material.base_color = planet_color(selected, planet);
+        }
+    }
+}
+
+// Selected planets stand out, the others take their constellation's color
+//  or are shaded by how central they are
+pub fn planet_color(selected: bool, planet: &PlanetComp) -> Color {
+    match (selected, planet.constellation_color, planet.emphasis) {
+        (true, _, _) => PLANET_SELECTED_COLOR,
+        (false, Some(color), _) => color,
+        (false, None, None) => PLANET_COLOR,
+        (false, None, Some(emphasis)) => {
+            let from = Vec4::from(PLANET_PERIPHERAL_COLOR);
+            let to = Vec4::from(PLANET_CENTRAL_COLOR);
+            Color::from(from.lerp(to, emphasis))
+        }
+    }
+}
+
+// How much bigger a planet is drawn, and how much further away its label shows
+pub fn emphasis_scale(emphasis: Option<f32>) -> f32 {
+    match emphasis {
+        Some(emphasis) => EMPHASIS_MIN_SCALE + (EMPHASIS_MAX_SCALE - EMPHASIS_MIN_SCALE) * emphasis,
+        None => 1.,
+    }
+}
+
+pub fn planet_scale(radius: f32, emphasis: Option<f32>) -> Vec3 {
+    Vec3::splat(radius / PLANET_RADIUS * emphasis_scale(emphasis))
+}
+
+// List skipped dataset files in the console and on screen once the scene is built
+#[allow(clippy::too_many_arguments)]
+fn report_load_errors(
+    mut labels_evr: EventReader<LabelsSpawned>,
+    mut commands: Commands,
+    errors: Res<LoadErrors>,
+    cartography: Res<CartographyRes>,
+    source: Res<DatasetSource>,
+    asset_server: Res<AssetServer>,
+    time: Res<Time>,
+    mut log: ResMut<ConsoleLog>,
+    warning_q: Query<Entity, With<LoadWarning>>,
+) {
+    if labels_evr.iter().count() == 0 {
+        return;
+    }
+    for warning in warning_q.iter() {
+        commands.entity(warning).despawn();
+    }
+    let mut lines: Vec<String> = errors
+        .0
+        .iter()
+        .map(|e| format!("[DATASET] Skipped {}", e))
+        .collect();
+    if cartography.galaxies.is_empty() {
+        lines.push(format!("[DATASET] No datasets loaded from {}", *source));
+    }
+    if lines.is_empty() {
+        return;
+    }
+    for line in lines.iter() {
+        log.warn(line);
+    }
+
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(10.),
+                    right: Val::Px(10.),
+                    ..Default::default()
+                },
+                max_size: Size::new(Val::Percent(50.), Val::Auto),
+                ..Default::default()
+            },
+            text: Text::with_section(
+                lines.join("\n"),
+                TextStyle {
+                    font: asset_server.load(FONT_PATH),
+                    font_size: WARNING_FONT_SIZE,
+                    color: Color::YELLOW,
+                },
+                TextAlignment::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(LoadWarning {
+            shown_at: time.seconds_since_startup(),
+        });
+}
+
+fn hide_load_warning(
+    time: Res<Time>,
+    mut commands: Commands,
+    warning_q: Query<(Entity, &LoadWarning)>,
+) {
+    for (entity, warning) in warning_q.iter() {
+        if (time.seconds_since_startup() - warning.shown_at) as f32 > LOAD_WARNING_SECONDS {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+// Spawn a planet at every computed position. Planets already spawned are kept,
+//  so reloading datasets only adds and removes the planets that changed
+#[allow(clippy::too_many_arguments)]
+fn spawn_planets(
+    mut positions_evr: EventReader<PositionsComputed>,
+    mut planets_evw: EventWriter<PlanetsSpawned>,
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     cartography: Res<CartographyRes>,
     mut mapping: ResMut<Index>,
-    asset_server: Res<AssetServer>,
+    selection: Res<Selection>,
+    planet_radius: Res<PlanetRadius>,
+    layout: Res<LayoutCache>,
+    mut planet_q: Query<(Entity, &mut PlanetComp, &mut Transform)>,
 ) {
-    let mut rng = thread_rng();
+    if positions_evr.iter().count() == 0 {
+        return;
+    }
+    for (entity, mut planet_comp, mut planet_tf) in planet_q.iter_mut() {
+        match cartography.planets.get(&planet_comp.name) {
+            Some(planet) => {
+                planet_comp.galaxies = planet.belong_galaxy.clone();
+                planet_tf.translation = Vec3::from(layout.positions[&planet_comp.name]);
+            }
+            None => {
+                commands.entity(entity).despawn();
+                mapping.name_to_planet.remove(&planet_comp.name);
+            }
+        }
+    }
+
     for (planet_name, planet) in &cartography.planets {
-        // choose a random position between 0 and RANDOM_SPACE_LIMIT
-        let x: f32 = rng.gen_range(0.0..RANDOM_SPACE_LIMIT);
-        let y: f32 = rng.gen_range(0.0..RANDOM_SPACE_LIMIT);
-        let z: f32 = rng.gen_range(0.0..RANDOM_SPACE_LIMIT);
+        if mapping.name_to_planet.contains_key(planet_name) {
+            continue;
+        }
+        let [x, y, z] = layout.positions[planet_name];
 
         // let planet_conn_weights: i32 = planet.conns.iter().map(|conn| conn.count).sum();
         // let radius = (planet_conn_weights as f32 / 10000.)
         //     * (PLANET_MAX_RADIUS - PLANET_MIN_RADIUS)
         //     + PLANET_MIN_RADIUS;
 
+        // highlight_selection only recolors when the selection changes,
+        //  emphasis and constellation colors are applied to new planets once they are spawned
+        let color = if Some(planet_name) == selection.planet.as_ref() {
+            PLANET_SELECTED_COLOR
+        } else {
+            PLANET_COLOR
+        };
         let planet_id = commands
             .spawn_bundle(PbrBundle {
                 mesh: meshes.add(Mesh::from(shape::Icosphere {
                     radius: PLANET_RADIUS,
                     subdivisions: PLANET_SUBDIVISIONS,
                 })),
-                material: materials.add(Color::WHITE.into()),
-                transform: Transform::from_xyz(x, y, z),
+                material: materials.add(color.into()),
+                transform: Transform::from_xyz(x, y, z)
+                    .with_scale(planet_scale(planet_radius.0, None)),
                 ..Default::default()
             })
-            .insert(PlanetComp)
+            .insert(PlanetComp {
+                name: planet_name.to_string(),
+                galaxies: planet.belong_galaxy.clone(),
+                emphasis: None,
+                constellation_color: None,
+            })
             .id();
 
-        let font = asset_server.load(FONT_PATH);
+        // println!("[{}] ({:.1},{:.1},{:.1})", planet_name, x, y, z);
+
+        mapping
+            .name_to_planet
+            .insert(planet_name.to_string(), planet_id);
+    }
+    planets_evw.send(PlanetsSpawned);
+}
+
+// Connect every pair of planets that share tags, keeping the connections that didn't change
+#[allow(clippy::too_many_arguments)]
+fn spawn_connections(
+    mut planets_evr: EventReader<PlanetsSpawned>,
+    mut conns_evw: EventWriter<ConnectionsSpawned>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    cartography: Res<CartographyRes>,
+    max_width: Res<ConnMaxWidth>,
+    layout: Res<LayoutCache>,
+    conn_q: Query<(Entity, &ConnectionComp)>,
+) {
+    if planets_evr.iter().count() == 0 {
+        return;
+    }
+    // One connection per pair of planets, with the weight each galaxy gives it.
+    //  Galaxies may list the two planets either way round
+    let mut wanted: HashMap<(&String, &String), BTreeMap<String, i32>> = HashMap::new();
+    for conn in cartography
+        .planets
+        .values()
+        .flat_map(|planet| planet.conns.iter())
+    {
+        let (a, b) = &conn.planet_pairs;
+        let pair = if a <= b { (a, b) } else { (b, a) };
+        let weight = wanted
+            .entry(pair)
+            .or_default()
+            .entry(conn.galaxy.clone())
+            .or_insert(conn.count);
+        *weight = (*weight).max(conn.count);
+    }
+    for (entity, conn) in conn_q.iter() {
+        let pair = (&conn.planets.0, &conn.planets.1);
+        if wanted.get(&pair) == Some(&conn.galaxies) {
+            wanted.remove(&pair);
+        } else {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    for ((planet_a_name, planet_b_name), galaxies) in wanted {
+        let planet_a_translation = Vec3::from(layout.positions[planet_a_name]);
+        let planet_b_translation = Vec3::from(layout.positions[planet_b_name]);
+
+        // find the middle point between a and b
+        let middle_vec = (planet_a_translation + planet_b_translation) / 2.;
+
+        // find distance between a and b as the length of cube
+        let dist = planet_a_translation.distance(planet_b_translation);
 
+        // connections are built one unit wide and scaled to their width,
+        //  so they can be resized without rebuilding the meshes
+        let weight = galaxies.values().copied().max().unwrap_or(0);
+        let width = conn_width(weight, max_width.0);
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::Box::new(1., dist, 1.))),
+                material: materials.add(Color::WHITE.into()),
+                transform: Transform {
+                    translation: middle_vec,
+                    rotation: Quat::from_rotation_arc(
+                        Vec3::Y,
+                        (planet_b_translation - planet_a_translation).normalize(),
+                    ),
+                    scale: Vec3::new(width, 1., width),
+                },
+                ..Default::default()
+            })
+            .insert(ConnectionComp {
+                planets: (planet_a_name.clone(), planet_b_name.clone()),
+                weight,
+                galaxies,
+            });
+    }
+    conns_evw.send(ConnectionsSpawned);
+}
+
+// Name every planet on screen, keeping the labels of planets that are still there
+fn spawn_labels(
+    mut conns_evr: EventReader<ConnectionsSpawned>,
+    mut labels_evw: EventWriter<LabelsSpawned>,
+    mut commands: Commands,
+    mut mapping: ResMut<Index>,
+    asset_server: Res<AssetServer>,
+) {
+    if conns_evr.iter().count() == 0 {
+        return;
+    }
+    let mapping = &mut *mapping;
+    let planets: HashSet<Entity> = mapping.name_to_planet.values().copied().collect();
+    mapping.label_to_planet.retain(|label, planet| {
+        if !planets.contains(planet) {
+            commands.entity(*label).despawn();
+        }
+        planets.contains(planet)
+    });
+    let labelled: HashSet<Entity> = mapping.label_to_planet.values().copied().collect();
+
+    let font: Handle<Font> = asset_server.load(FONT_PATH);
+    for (planet_name, planet_id) in mapping.name_to_planet.iter() {
+        if labelled.contains(planet_id) {
+            continue;
+        }
         let label_id = commands
             .spawn_bundle(TextBundle {
                 style: Style {
@@ -212,7 +637,7 @@ fn setup_planets(
                 text: Text::with_section(
                     planet_name,
                     TextStyle {
-                        font,
+                        font: font.clone(),
                         font_size: FONT_SIZE_DEFAULT,
                         color: FONT_COLOR,
                     },
@@ -226,66 +651,40 @@ fn setup_planets(
             })
             .insert(PlanetLabel)
             .id();
+        mapping.label_to_planet.insert(label_id, *planet_id);
+    }
+    labels_evw.send(LabelsSpawned);
+}
 
-        // println!("[{}] ({:.1},{:.1},{:.1})", planet_name, x, y, z);
-
-        mapping.label_to_planet.insert(label_id, planet_id);
-        mapping
-            .name_to_planet
-            .insert(planet_name.to_string(), planet_id);
+// calculate connection width based on its weight
+fn conn_width(weight: i32, max_width: f32) -> f32 {
+    match weight {
+        0..=2 => CONN_MIN_WIDTH,
+        1000.. => max_width,
+        x => (max_width - CONN_MIN_WIDTH) * (x as f32 / 1000.) + CONN_MIN_WIDTH,
     }
 }
 
-fn setup_planetary_connections(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    asset_server: Res<AssetServer>,
-    cartography: Res<CartographyRes>,
-    index: Res<Index>,
-    planet_q: Query<&Transform, With<PlanetComp>>,
+// Apply size cvars to planets and connections that already exist
+fn resize_scene(
+    mut changed_evr: EventReader<CvarChanged>,
+    mut planet_q: Query<(&PlanetComp, &mut Transform), Without<ConnectionComp>>,
+    mut conn_q: Query<(&ConnectionComp, &mut Transform), Without<PlanetComp>>,
 ) {
-    for (planet_a_name, planet_a) in &cartography.planets {
-        let planet_a_entity = index.name_to_planet.get(planet_a_name).unwrap();
-        let planet_a_transform = planet_q.get(*planet_a_entity).unwrap();
-
-        // generate lines for each planet connection
-        for conn in &planet_a.conns {
-            let (_, planet_b_name) = &conn.planet_pairs;
-            let planet_b_entity = index.name_to_planet.get(planet_b_name).unwrap();
-            let planet_b_transform = planet_q.get(*planet_b_entity).unwrap();
-            // calculate the direction between planet a and b
-            let rot = planet_a_transform.translation - planet_b_transform.translation;
-
-            // find the middle point between a and b
-            let middle_vec = (planet_a_transform.translation + planet_b_transform.translation) / 2.;
-
-            // find distance between a and b as the length of cube
-            let dist = planet_a_transform
-                .translation
-                .distance(planet_b_transform.translation);
-
-            // calculate connection width based on its weight
-            let weight = conn.count;
-            let width: f32 = match weight {
-                0..=2 => CONN_MIN_WIDTH,
-                1000.. => CONN_MAX_WIDTH,
-                x => (CONN_MAX_WIDTH - CONN_MIN_WIDTH) * (x as f32 / 1000.) + CONN_MIN_WIDTH,
-            };
-            commands.spawn_bundle(PbrBundle {
-                mesh: meshes.add(Mesh::from(shape::Box::new(width, dist, width))),
-                material: materials.add(Color::WHITE.into()),
-                transform: Transform {
-                    translation: middle_vec,
-                    rotation: Quat::from_rotation_arc(
-                        Vec3::Y,
-                        (planet_b_transform.translation - planet_a_transform.translation)
-                            .normalize(),
-                    ),
-                    ..Default::default()
-                },
-                ..Default::default()
-            });
+    for ev in changed_evr.iter() {
+        match ev.name {
+            PlanetRadius::NAME => {
+                for (planet, mut planet_tf) in planet_q.iter_mut() {
+                    planet_tf.scale = planet_scale(ev.value, planet.emphasis);
+                }
+            }
+            ConnMaxWidth::NAME => {
+                for (conn, mut conn_tf) in conn_q.iter_mut() {
+                    let width = conn_width(conn.weight, ev.value);
+                    conn_tf.scale = Vec3::new(width, 1., width);
+                }
+            }
+            _ => {}
         }
     }
 }
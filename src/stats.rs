@@ -0,0 +1,232 @@
+// graph statistics of the universe, measured in the background whenever it is rebuilt
+use std::sync::{
+    mpsc::{channel, Receiver},
+    Mutex,
+};
+
+use bevy::{prelude::*, tasks::AsyncComputeTaskPool};
+
+use crate::{
+    analysis::{GalaxyStats, GraphStats, UniverseGraphs, UniverseStats},
+    console::{AddConsoleCommand, Arg, CommandEvent, CommandSpec, ConsoleLog},
+    controls::{Action, Actions, OpenMenu},
+    menu::{menu_sections, spawn_menu_panel, PanelAnchor},
+    pipeline::UniverseLoaded,
+    scene::CartographyRes,
+};
+
+const FONT_SIZE: f32 = 14.;
+const HELP: &str = "F4: close\n\n";
+
+// Statistics of the current universe, None while they are being measured
+#[derive(Default)]
+pub struct Stats {
+    pub universe: Option<UniverseStats>,
+    receiver: Option<Mutex<Receiver<UniverseStats>>>,
+}
+
+pub enum StatsRequest {
+    Toggle,
+    // Print the statistics of the universe, or of a single galaxy
+    Print(Option<String>),
+}
+
+#[derive(Component, Clone)]
+struct StatsPanel;
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Stats>()
+            .add_event::<StatsRequest>()
+            .add_console_command(
+                CommandSpec::new("stats")
+                    .usage(
+                        &[],
+                        "Show graph statistics of every galaxy and the universe",
+                    )
+                    .usage(&[Arg::Text("galaxy")], "Show graph statistics of a galaxy"),
+            )
+            .add_startup_system(setup_stats_panel)
+            .add_system(start_stats)
+            .add_system(receive_stats)
+            .add_system(stats_control)
+            .add_system(stats_command)
+            .add_system(handle_stats)
+            .add_system(update_stats_panel);
+    }
+}
+
+fn describe_graph(graph: &GraphStats) -> Vec<String> {
+    vec![
+        format!(
+            "  {} planets, {} connections",
+            graph.planets, graph.connections
+        ),
+        format!(
+            "  {} components, the largest has {} planets, diameter ~{} hops",
+            graph.components, graph.largest_component, graph.diameter
+        ),
+        format!("  clustering coefficient {:.3}", graph.clustering),
+        format!(
+            "  degree: {}  [{}]",
+            graph.degree,
+            graph.degree.histogram_text()
+        ),
+        format!(
+            "  weight: {}  [{}]",
+            graph.weight,
+            graph.weight.histogram_text()
+        ),
+    ]
+}
+
+pub fn describe_galaxy(galaxy: &GalaxyStats) -> Vec<String> {
+    let mut lines = vec![galaxy.name.clone()];
+    lines.extend(describe_graph(&galaxy.graph));
+    let shared = if galaxy.shared_tags.is_empty() {
+        "none".to_string()
+    } else {
+        galaxy
+            .shared_tags
+            .iter()
+            .map(|(other, tags)| format!("{} {}", other, tags))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    lines.push(format!("  tags shared with: {}", shared));
+    lines
+}
+
+// Every galaxy, then the universe as a whole
+pub fn describe_universe(universe: &UniverseStats) -> Vec<String> {
+    let mut lines = vec![];
+    for galaxy in universe.galaxies.iter() {
+        lines.extend(describe_galaxy(galaxy));
+    }
+    lines.push("universe".to_string());
+    lines.extend(describe_graph(&universe.graph));
+    lines.push(format!(
+        "  {} planets shared between galaxies",
+        universe.shared_planets
+    ));
+    lines
+}
+
+fn setup_stats_panel(mut commands: Commands, asset_server: Res<AssetServer>) {
+    spawn_menu_panel(
+        &mut commands,
+        &asset_server,
+        PanelAnchor::TopLeft,
+        FONT_SIZE,
+        StatsPanel,
+    );
+}
+
+// Measure the universe on the task pool, the graphs are copied out so it can be replaced meanwhile
+fn start_stats(
+    mut loaded_evr: EventReader<UniverseLoaded>,
+    pool: Res<AsyncComputeTaskPool>,
+    cartography: Res<CartographyRes>,
+    mut stats: ResMut<Stats>,
+) {
+    if loaded_evr.iter().count() == 0 {
+        return;
+    }
+    let graphs = UniverseGraphs::new(&cartography.galaxies, &cartography.planets);
+    let (sender, receiver) = channel();
+    // Results of an older universe still being measured are dropped with the old receiver
+    *stats = Stats {
+        universe: None,
+        receiver: Some(Mutex::new(receiver)),
+    };
+    pool.spawn(async move {
+        let _ = sender.send(graphs.stats());
+    })
+    .detach();
+}
+
+fn receive_stats(mut stats: ResMut<Stats>) {
+    let universe = match &stats.receiver {
+        Some(receiver) => match receiver.lock().unwrap().try_recv() {
+            Ok(universe) => universe,
+            Err(_) => return,
+        },
+        None => return,
+    };
+    stats.receiver = None;
+    stats.universe = Some(universe);
+}
+
+fn stats_control(actions: Res<Actions>, mut requests: EventWriter<StatsRequest>) {
+    if actions.just_pressed(Action::ToggleStats) {
+        requests.send(StatsRequest::Toggle);
+    }
+}
+
+// stats [galaxy]
+fn stats_command(mut events: EventReader<CommandEvent>, mut requests: EventWriter<StatsRequest>) {
+    for ev in events.iter().filter(|ev| ev.name == "stats") {
+        requests.send(StatsRequest::Print(ev.args.first().cloned()));
+    }
+}
+
+fn handle_stats(
+    mut requests: EventReader<StatsRequest>,
+    mut menu: ResMut<OpenMenu>,
+    mut log: ResMut<ConsoleLog>,
+    stats: Res<Stats>,
+) {
+    for request in requests.iter() {
+        let name = match request {
+            StatsRequest::Toggle => {
+                menu.toggle(OpenMenu::Stats);
+                continue;
+            }
+            StatsRequest::Print(name) => name,
+        };
+        let universe = match &stats.universe {
+            Some(universe) => universe,
+            None => {
+                log.warn("[STATS] Still measuring the universe, try again in a moment");
+                continue;
+            }
+        };
+        let lines = match name {
+            Some(name) => match universe.galaxies.iter().find(|g| g.name == *name) {
+                Some(galaxy) => describe_galaxy(galaxy),
+                None => {
+                    log.error(format!("[STATS] Unknown galaxy: {}", name));
+                    continue;
+                }
+            },
+            None => describe_universe(universe),
+        };
+        for line in lines {
+            log.info(line);
+        }
+    }
+}
+
+fn update_stats_panel(
+    stats: Res<Stats>,
+    menu: Res<OpenMenu>,
+    mut panel_q: Query<&mut Visibility, With<StatsPanel>>,
+    mut text_q: Query<&mut Text, With<StatsPanel>>,
+) {
+    if !stats.is_changed() && !menu.is_changed() {
+        return;
+    }
+    for mut visibility in panel_q.iter_mut() {
+        visibility.is_visible = *menu == OpenMenu::Stats;
+    }
+
+    let mut text = text_q.single_mut();
+    let style = text.sections[0].style.clone();
+    let body = match &stats.universe {
+        Some(universe) => describe_universe(universe).join("\n"),
+        None => "Measuring the universe...".to_string(),
+    };
+    text.sections = menu_sections(&style, HELP, [(body, Color::WHITE)]);
+}
@@ -0,0 +1,330 @@
+// scripted camera tours through the universe with on-screen captions
+use std::fs;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    camera::{look_angles, CameraFlight, MouseState, MoveCamera, SceneCam},
+    console::{AddConsoleCommand, Arg, CommandEvent, CommandSpec, ConsoleLog},
+    scene::{PlanetComp, PlanetRadius},
+};
+
+const TOUR_DIR: &str = "tours/";
+const FONT_PATH: &str = "fonts/FiraSans-Bold.ttf";
+const CAPTION_FONT_SIZE: f32 = 32.;
+// How far from a planet the camera stops when visiting it, in planet radii
+const PLANET_VIEW_DISTANCE: f32 = 12.;
+const DEFAULT_TRAVEL: f32 = 3.;
+const DEFAULT_DWELL: f32 = 4.;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Target {
+    // Fly towards a planet by name and look at it
+    Planet(String),
+    // Fly to an exact viewpoint
+    View {
+        position: [f32; 3],
+        yaw: f32,
+        pitch: f32,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Waypoint {
+    pub target: Target,
+    // Seconds spent flying to the waypoint
+    #[serde(default = "default_travel")]
+    pub travel: f32,
+    // Seconds spent at the waypoint before moving on
+    #[serde(default = "default_dwell")]
+    pub dwell: f32,
+    #[serde(default)]
+    pub caption: String,
+}
+
+fn default_travel() -> f32 {
+    DEFAULT_TRAVEL
+}
+
+fn default_dwell() -> f32 {
+    DEFAULT_DWELL
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Tour {
+    pub waypoints: Vec<Waypoint>,
+}
+
+impl Tour {
+    // Names come from the console, so they must not reach outside the tours directory
+    fn path(name: &str) -> Result<String, String> {
+        if name.is_empty() || name.contains(['/', '\\']) || name.contains("..") {
+            return Err(format!(
+                "invalid name '{}', it must not be empty or contain / \\ or ..",
+                name
+            ));
+        }
+        Ok(format!("{}{}.ron", TOUR_DIR, name))
+    }
+
+    fn load(name: &str) -> Result<Tour, String> {
+        let path = Tour::path(name)?;
+        let content = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path, e))?;
+        ron::from_str(&content).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    // Path of the saved tour
+    fn save(&self, name: &str) -> Result<String, String> {
+        let content = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| e.to_string())?;
+        let path = Tour::path(name)?;
+        fs::create_dir_all(TOUR_DIR).map_err(|e| e.to_string())?;
+        fs::write(&path, content).map_err(|e| format!("{}: {}", path, e))?;
+        Ok(path)
+    }
+}
+
+// Tour currently being played, and how far along it is
+#[derive(Default)]
+struct TourPlayer {
+    tour: Option<Tour>,
+    waypoint: usize,
+    // Time spent on the current waypoint, including travel
+    elapsed: f32,
+    started: bool,
+}
+
+// Waypoints recorded from the console, waiting to be saved
+#[derive(Default)]
+struct TourRecorder {
+    tour: Tour,
+}
+
+#[derive(Component)]
+struct Caption;
+
+pub struct TourPlugin;
+
+impl Plugin for TourPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TourPlayer::default())
+            .insert_resource(TourRecorder::default())
+            .add_console_command(
+                CommandSpec::new("tour")
+                    .usage(
+                        &[Arg::Literal("play"), Arg::Text("name")],
+                        "Play tours/<name>.ron",
+                    )
+                    .usage(&[Arg::Literal("stop")], "Stop the tour being played")
+                    .usage(
+                        &[Arg::Literal("record"), Arg::Rest("caption")],
+                        "Add the current view as a waypoint",
+                    )
+                    .usage(
+                        &[Arg::Literal("save"), Arg::Text("name")],
+                        "Save recorded waypoints to tours/<name>.ron",
+                    )
+                    .usage(&[Arg::Literal("clear")], "Discard recorded waypoints"),
+            )
+            .add_startup_system(setup_caption)
+            .add_system(tour_command)
+            .add_system(play_tour);
+    }
+}
+
+fn setup_caption(mut commands: Commands, asset_server: Res<AssetServer>) {
+    // Full width strip above the console so the caption can be centred
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(60.),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    visibility: Visibility { is_visible: false },
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font_size: CAPTION_FONT_SIZE,
+                            font: asset_server.load(FONT_PATH),
+                            color: Color::WHITE,
+                        },
+                        Default::default(),
+                    ),
+                    ..Default::default()
+                })
+                .insert(Caption);
+        });
+}
+
+// tour play <name>
+// tour stop
+// tour record [caption...]
+// tour save <name>
+// tour clear
+fn tour_command(
+    mut events: EventReader<CommandEvent>,
+    mut player: ResMut<TourPlayer>,
+    mut recorder: ResMut<TourRecorder>,
+    mut log: ResMut<ConsoleLog>,
+    mouse_state: Res<MouseState>,
+    mut flight: ResMut<Option<CameraFlight>>,
+    camera_q: Query<&Transform, With<SceneCam>>,
+) {
+    for ev in events.iter().filter(|ev| ev.name == "tour") {
+        let args: Vec<&str> = ev.args.iter().map(|a| a.as_str()).collect();
+        match args.as_slice() {
+            ["play", name] => match Tour::load(name) {
+                Ok(tour) => {
+                    log.info(format!(
+                        "[TOUR] Playing {} ({} waypoints)",
+                        name,
+                        tour.waypoints.len()
+                    ));
+                    *player = TourPlayer {
+                        tour: Some(tour),
+                        ..Default::default()
+                    };
+                }
+                Err(e) => log.error(format!("[TOUR] Failed to load {}", e)),
+            },
+            ["stop"] => {
+                player.tour = None;
+                // Don't keep flying to the waypoint the tour was heading for
+                *flight = None;
+                log.info("[TOUR] Stopped");
+            }
+            ["record", caption @ ..] => {
+                let cam_tf = camera_q.single();
+                recorder.tour.waypoints.push(Waypoint {
+                    target: Target::View {
+                        position: cam_tf.translation.into(),
+                        yaw: mouse_state.yaw,
+                        pitch: mouse_state.pitch,
+                    },
+                    travel: DEFAULT_TRAVEL,
+                    dwell: DEFAULT_DWELL,
+                    caption: caption.join(" "),
+                });
+                log.info(format!(
+                    "[TOUR] Recorded waypoint {}",
+                    recorder.tour.waypoints.len()
+                ));
+            }
+            ["save", name] => match recorder.tour.save(name) {
+                Ok(path) => log.info(format!("[TOUR] Saved {}", path)),
+                Err(e) => log.error(format!("[TOUR] Failed to save {}", e)),
+            },
+            ["clear"] => {
+                recorder.tour.waypoints.clear();
+                log.info("[TOUR] Cleared recording");
+            }
+            _ => {}
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn play_tour(
+    time: Res<Time>,
+    mut player: ResMut<TourPlayer>,
+    mut move_evw: EventWriter<MoveCamera>,
+    mut log: ResMut<ConsoleLog>,
+    planet_radius: Res<PlanetRadius>,
+    mut caption_q: Query<(&mut Text, &mut Visibility), With<Caption>>,
+    camera_q: Query<&Transform, With<SceneCam>>,
+    planet_q: Query<(&PlanetComp, &Transform), Without<SceneCam>>,
+) {
+    let (mut caption, mut caption_visibility) = caption_q.single_mut();
+    let player = &mut *player;
+
+    let tour = match &player.tour {
+        Some(tour) => tour,
+        None => {
+            caption_visibility.is_visible = false;
+            return;
+        }
+    };
+    let waypoint = match tour.waypoints.get(player.waypoint) {
+        Some(waypoint) => waypoint,
+        None => {
+            log.info("[TOUR] Finished");
+            player.tour = None;
+            caption_visibility.is_visible = false;
+            return;
+        }
+    };
+
+    if !player.started {
+        let cam_tf = camera_q.single();
+        let destination = match &waypoint.target {
+            Target::View {
+                position,
+                yaw,
+                pitch,
+            } => Some((Vec3::from(*position), *yaw, *pitch)),
+            Target::Planet(name) => planet_q
+                .iter()
+                .find(|(planet, _)| planet.name == *name)
+                .map(|(_, planet_tf)| {
+                    // Approach the planet from wherever the camera currently is
+                    let direction = (planet_tf.translation - cam_tf.translation)
+                        .try_normalize()
+                        .unwrap_or(-Vec3::Z);
+                    let (yaw, pitch) = look_angles(direction);
+                    let position =
+                        planet_tf.translation - direction * PLANET_VIEW_DISTANCE * planet_radius.0;
+                    (position, yaw, pitch)
+                }),
+        };
+        match destination {
+            Some((position, yaw, pitch)) => move_evw.send(MoveCamera {
+                position,
+                yaw,
+                pitch,
+                duration: waypoint.travel,
+            }),
+            None => log.warn(format!(
+                "[TOUR] Skipping unknown target {:?}",
+                waypoint.target
+            )),
+        }
+        caption.sections[0].value = waypoint.caption.clone();
+        caption_visibility.is_visible = !waypoint.caption.is_empty();
+        player.started = true;
+    }
+
+    player.elapsed += time.delta_seconds();
+    if player.elapsed >= waypoint.travel + waypoint.dwell {
+        player.waypoint += 1;
+        player.elapsed = 0.;
+        player.started = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tour_names_stay_in_the_tours_directory() {
+        assert_eq!(Tour::path("intro"), Ok("tours/intro.ron".to_string()));
+        for name in ["", "../x", "../../x", "a/b", "a\\b", ".."] {
+            assert!(Tour::path(name).is_err(), "{}", name);
+        }
+        assert!(Tour::default().save("../escaped").is_err());
+        assert!(!std::path::Path::new("escaped.ron").exists());
+    }
+}